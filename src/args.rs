@@ -0,0 +1,438 @@
+use chrono::{DateTime, FixedOffset};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use mini_nu::config::CONFIG_FILE;
+use mini_nu::input::InputFormat;
+use mini_nu::lint::RuleConfig;
+use mini_nu::profiles::Profile;
+use mini_nu::secrets::SecretProvider;
+
+/// Command-line options for the `mini-nu` binary.
+pub struct Args {
+    /// Run the `lint` subcommand on the snippet instead of evaluating it.
+    pub lint: bool,
+    /// Print the engine's capability report instead of evaluating anything.
+    pub list_capabilities: bool,
+    /// Print how long building the engine took, phase by phase, to stderr.
+    pub startup_report: bool,
+    pub code_snippet: Option<String>,
+    /// `install <path>`, `list` or `run <name>`.
+    pub registry: Option<RegistryCommand>,
+    /// Let `install` overwrite a closure already installed under the same name.
+    pub replace: bool,
+    /// Registry directory, overriding the default under the user's config dir.
+    pub registry_dir: Option<PathBuf>,
+    /// File to read the code from instead, from `--closure-file`.
+    pub closure_file: Option<PathBuf>,
+    /// Read stdin in this format and pass it to the snippet as its input.
+    pub stdin_format: Option<InputFormat>,
+    /// Size of `bytes` input chunks, as a nu filesize, e.g. `64kb`.
+    pub chunk_size: Option<String>,
+    /// Read stdin as the snippet consumes it, rather than all of it up front.
+    pub stream: bool,
+    /// Byte ending each line of line-based input, from `--stdin-delimiter newline|nul`.
+    pub stdin_delimiter: u8,
+    /// Where NDJSON input lines that don't parse go, instead of failing: `stderr` or a file.
+    pub malformed_lines: Option<String>,
+    /// IANA timezone name exported as `TZ` before the engine is created.
+    pub timezone: Option<String>,
+    /// Locale name (e.g. `de_DE.UTF-8`) exported as `LC_ALL` before the engine is created.
+    pub locale: Option<String>,
+    /// Deny commands whose output depends on the host, the clock or randomness.
+    pub sandbox: bool,
+    /// Deny commands that write to the filesystem or spawn processes.
+    pub read_only: bool,
+    pub deny_network: bool,
+    pub deny_filesystem: bool,
+    /// Command groups to build the engine from instead of the full shell, from `--profile a,b`.
+    pub profiles: Vec<Profile>,
+    /// Only commands listed in this file (one per line, `#` for comments) stay available.
+    pub allow_file: Option<PathBuf>,
+    /// Commands to deny, from `--deny a,b`.
+    pub deny: Vec<String>,
+    /// Directory `open`, `ls` and `save` are limited to, from `--root`.
+    pub root: Option<PathBuf>,
+    /// Abort evaluation once it has used this much CPU time, from `--max-cpu-ms`.
+    pub max_cpu: Option<Duration>,
+    /// Abort evaluation once it has allocated this many bytes, from `--max-memory-mb`.
+    pub max_memory: Option<usize>,
+    /// Wall-clock limit on evaluation, from `--timeout-ms`.
+    pub timeout: Option<Duration>,
+    /// Instant `date now` returns, in and out of sandbox mode.
+    pub freeze_time: Option<DateTime<FixedOffset>>,
+    /// Backing store for `secret get`, overriding the config file's `[secrets]`.
+    pub secrets: Option<SecretProvider>,
+    pub config: PathBuf,
+    /// Tables for `lookup`, from `--lookup name=path.csv`.
+    pub lookups: Vec<(String, PathBuf)>,
+    /// Files for `$datasets`, from `--dataset name=path`.
+    pub datasets: Vec<(String, PathBuf)>,
+    /// Strings for `$args`, from `--arg key=value`.
+    pub args: Vec<(String, String)>,
+    /// Largest dataset file to load, from `--dataset-max-mb`.
+    pub dataset_max_bytes: Option<u64>,
+    /// MaxMind database backing `geoip lookup`.
+    #[cfg(feature = "geoip")]
+    pub geoip_db: Option<PathBuf>,
+    /// uap-core `regexes.yaml` backing `ua parse`.
+    #[cfg(feature = "useragent")]
+    pub ua_regexes: Option<PathBuf>,
+    /// Regexes for `extract`, from `--pattern name=regex`.
+    pub patterns: Vec<(String, String)>,
+    /// Column the lookup tables are indexed by.
+    pub lookup_key: String,
+    pub on_epipe: OnEpipe,
+    /// Predicate closure every result must satisfy.
+    pub expect: Option<String>,
+    /// Closure every result is passed through before it's written, optionally taking the
+    /// result's index as a second argument.
+    pub post: Option<String>,
+    /// Closure that shapes the report of a failed evaluation, or suppresses it by returning null.
+    pub on_error: Option<String>,
+    /// Drop results whose key was already written within this nu duration, e.g. `5min`.
+    pub suppress_duplicates: Option<String>,
+    /// Closure computing a result's key for `--suppress-duplicates`; the whole result otherwise.
+    pub dup_key: Option<String>,
+    /// Send the run's outcome to the config's `[notify]` sink when it fails, from
+    /// `--notify on-failure`.
+    pub notify_on_failure: bool,
+    /// Send the run's outcome whether or not it failed, from `--notify on-complete`.
+    pub notify_on_complete: bool,
+    /// Pipeline run once over all the results, as a table, whose output is written instead.
+    pub report: Option<String>,
+    /// Closures every result is also run through side by side, from `--tee name={closure}`;
+    /// their outputs are written labeled with the name in place of the result.
+    pub tees: Vec<(String, String)>,
+    pub format: Format,
+    /// Severity overrides for `lint`, from `--rule name=severity`.
+    pub rules: RuleConfig,
+}
+
+/// A subcommand managing the closure registry.
+#[derive(Clone, PartialEq, Eq)]
+pub enum RegistryCommand {
+    /// Validate a closure file and copy it into the registry.
+    Install(PathBuf),
+    List,
+    /// Run an installed closure in place of a code snippet.
+    Run(String),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+    /// One JSON document per result.
+    Ndjson,
+}
+
+/// What to do when stdout is closed before all output is written.
+#[derive(Clone, Copy)]
+pub enum OnEpipe {
+    /// Exit with status 141, as if killed by SIGPIPE.
+    Exit,
+    /// Drop the remaining output and exit normally.
+    Ignore,
+}
+
+impl Args {
+    pub fn parse() -> Result<Self, String> {
+        let mut lint = false;
+        let mut code_snippet = None;
+        let mut closure_file = None;
+        let mut registry = None;
+        let mut replace = false;
+        let mut registry_dir = None;
+        let mut stdin_format = None;
+        let mut malformed_lines = None;
+        let mut stream = false;
+        let mut chunk_size = None;
+        let mut stdin_delimiter = b'\n';
+        let mut timezone = None;
+        let mut locale = None;
+        let mut sandbox = false;
+        let mut read_only = false;
+        let mut deny_network = false;
+        let mut deny_filesystem = false;
+        let mut list_capabilities = false;
+        let mut startup_report = false;
+        let mut profiles = vec![];
+        let mut allow_file = None;
+        let mut deny = vec![];
+        let mut root = None;
+        let mut max_cpu = None;
+        let mut max_memory = None;
+        let mut timeout = None;
+        let mut freeze_time = None;
+        let mut secrets = None;
+        let mut config = PathBuf::from(CONFIG_FILE);
+        let mut lookups = vec![];
+        let mut lookup_key = "id".to_string();
+        let mut datasets = vec![];
+        let mut named_args = vec![];
+        let mut patterns = vec![];
+        #[cfg(feature = "geoip")]
+        let mut geoip_db = None;
+        #[cfg(feature = "useragent")]
+        let mut ua_regexes = None;
+        let mut dataset_max_bytes = None;
+        let mut on_epipe = OnEpipe::Exit;
+        let mut expect = None;
+        let mut post = None;
+        let mut on_error = None;
+        let mut report = None;
+        let mut notify_on_failure = false;
+        let mut notify_on_complete = false;
+        let mut suppress_duplicates = None;
+        let mut dup_key = None;
+        let mut tees = vec![];
+        let mut format = Format::Text;
+        let mut rules = RuleConfig::default();
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--registry-dir" => registry_dir = Some(value(&mut args, &arg)?.into()),
+                "--closure-file" => closure_file = Some(value(&mut args, &arg)?.into()),
+                "--stdin-format" => stdin_format = Some(value(&mut args, &arg)?.parse()?),
+                "--stream" => stream = true,
+                "--chunk-size" => chunk_size = Some(value(&mut args, &arg)?),
+                "--stdin-delimiter" => {
+                    stdin_delimiter = match value(&mut args, &arg)?.as_str() {
+                        "newline" => b'\n',
+                        "nul" => b'\0',
+                        other => {
+                            return Err(format!(
+                                "Invalid --stdin-delimiter '{other}', expected newline or nul"
+                            ))
+                        }
+                    }
+                }
+                "--malformed-lines" => malformed_lines = Some(value(&mut args, &arg)?),
+                "--timezone" => {
+                    let name = value(&mut args, &arg)?;
+                    name.parse::<chrono_tz::Tz>()
+                        .map_err(|_| format!("Invalid --timezone '{name}': unknown timezone"))?;
+                    timezone = Some(name);
+                }
+                "--locale" => locale = Some(value(&mut args, &arg)?),
+                "--sandbox" => sandbox = true,
+                "--read-only" => read_only = true,
+                "--deny-network" => deny_network = true,
+                "--deny-filesystem" => deny_filesystem = true,
+                "--list-capabilities" => list_capabilities = true,
+                "--startup-report" => startup_report = true,
+                "--replace" => replace = true,
+                "--profile" => {
+                    for profile in value(&mut args, &arg)?.split(',') {
+                        profiles.push(profile.trim().parse()?);
+                    }
+                }
+                "--allow-file" => allow_file = Some(value(&mut args, &arg)?.into()),
+                "--deny" => {
+                    let names = value(&mut args, &arg)?;
+                    deny.extend(names.split(',').map(|name| name.trim().to_string()));
+                }
+                "--root" => root = Some(value(&mut args, &arg)?.into()),
+                "--max-cpu-ms" => {
+                    let ms = value(&mut args, &arg)?;
+                    let ms = ms
+                        .parse()
+                        .map_err(|err| format!("Invalid --max-cpu-ms '{ms}': {err}"))?;
+                    max_cpu = Some(Duration::from_millis(ms));
+                }
+                "--max-memory-mb" => {
+                    let mb = value(&mut args, &arg)?;
+                    let mb: usize = mb
+                        .parse()
+                        .map_err(|err| format!("Invalid --max-memory-mb '{mb}': {err}"))?;
+                    max_memory = Some(mb.saturating_mul(1024 * 1024));
+                }
+                "--timeout-ms" => {
+                    let ms = value(&mut args, &arg)?;
+                    let ms = ms
+                        .parse()
+                        .map_err(|err| format!("Invalid --timeout-ms '{ms}': {err}"))?;
+                    timeout = Some(Duration::from_millis(ms));
+                }
+                "--freeze-time" => {
+                    let ts = value(&mut args, &arg)?;
+                    let ts = DateTime::parse_from_rfc3339(&ts)
+                        .map_err(|err| format!("Invalid --freeze-time '{ts}': {err}"))?;
+                    freeze_time = Some(ts);
+                }
+                "--secrets" => secrets = Some(SecretProvider::parse(&value(&mut args, &arg)?)?),
+                "--config" => config = value(&mut args, &arg)?.into(),
+                "--lookup" => {
+                    let lookup = value(&mut args, &arg)?;
+                    let (name, path) = lookup.split_once('=').ok_or_else(|| {
+                        format!("Invalid --lookup '{lookup}', expected name=path")
+                    })?;
+                    lookups.push((name.to_string(), path.into()));
+                }
+                "--lookup-key" => lookup_key = value(&mut args, &arg)?,
+                #[cfg(feature = "geoip")]
+                "--geoip-db" => geoip_db = Some(value(&mut args, &arg)?.into()),
+                #[cfg(feature = "useragent")]
+                "--ua-regexes" => ua_regexes = Some(value(&mut args, &arg)?.into()),
+                "--pattern" => {
+                    let pattern = value(&mut args, &arg)?;
+                    let (name, regex) = pattern.split_once('=').ok_or_else(|| {
+                        format!("Invalid --pattern '{pattern}', expected name=regex")
+                    })?;
+                    patterns.push((name.to_string(), regex.to_string()));
+                }
+                "--dataset" => {
+                    let dataset = value(&mut args, &arg)?;
+                    let (name, path) = dataset.split_once('=').ok_or_else(|| {
+                        format!("Invalid --dataset '{dataset}', expected name=path")
+                    })?;
+                    datasets.push((name.to_string(), path.into()));
+                }
+                "--arg" => {
+                    let arg = value(&mut args, &arg)?;
+                    let (key, value) = arg
+                        .split_once('=')
+                        .ok_or_else(|| format!("Invalid --arg '{arg}', expected key=value"))?;
+                    named_args.push((key.to_string(), value.to_string()));
+                }
+                "--dataset-max-mb" => {
+                    let mb = value(&mut args, &arg)?;
+                    let mb: u64 = mb
+                        .parse()
+                        .map_err(|err| format!("Invalid --dataset-max-mb '{mb}': {err}"))?;
+                    dataset_max_bytes = Some(mb.saturating_mul(1024 * 1024));
+                }
+                "--on-epipe" => {
+                    on_epipe = match value(&mut args, &arg)?.as_str() {
+                        "exit" => OnEpipe::Exit,
+                        "ignore" => OnEpipe::Ignore,
+                        other => {
+                            return Err(format!(
+                                "Invalid --on-epipe '{other}', expected exit or ignore"
+                            ))
+                        }
+                    }
+                }
+                "--expect" => expect = Some(value(&mut args, &arg)?),
+                "--post" => post = Some(value(&mut args, &arg)?),
+                "--on-error" => on_error = Some(value(&mut args, &arg)?),
+                "--report" => report = Some(value(&mut args, &arg)?),
+                "--notify" => {
+                    for on in value(&mut args, &arg)?.split(',') {
+                        match on {
+                            "on-failure" => notify_on_failure = true,
+                            "on-complete" => notify_on_complete = true,
+                            other => {
+                                return Err(format!(
+                                    "Invalid --notify '{other}', expected on-failure or on-complete"
+                                ))
+                            }
+                        }
+                    }
+                }
+                "--suppress-duplicates" => suppress_duplicates = Some(value(&mut args, &arg)?),
+                "--dup-key" => dup_key = Some(value(&mut args, &arg)?),
+                "--tee" => {
+                    let tee = value(&mut args, &arg)?;
+                    let (name, closure) = tee.split_once('=').ok_or_else(|| {
+                        format!("Invalid --tee '{tee}', expected name={{closure}}")
+                    })?;
+                    tees.push((name.to_string(), closure.to_string()));
+                }
+                "--format" => {
+                    format = match value(&mut args, &arg)?.as_str() {
+                        "text" => Format::Text,
+                        "json" => Format::Json,
+                        "ndjson" => Format::Ndjson,
+                        other => {
+                            return Err(format!(
+                                "Invalid --format '{other}', expected text, json or ndjson"
+                            ))
+                        }
+                    }
+                }
+                "--rule" => {
+                    let rule = value(&mut args, &arg)?;
+                    let (name, severity) = rule.split_once('=').ok_or_else(|| {
+                        format!("Invalid --rule '{rule}', expected name=severity")
+                    })?;
+                    rules.set(name, severity.parse()?)?;
+                }
+                flag if flag.starts_with("--") => return Err(format!("Unknown option: {flag}")),
+                "lint" if !lint && code_snippet.is_none() && registry.is_none() => lint = true,
+                "install" if !lint && code_snippet.is_none() && registry.is_none() => {
+                    registry = Some(RegistryCommand::Install(value(&mut args, &arg)?.into()))
+                }
+                "list" if !lint && code_snippet.is_none() && registry.is_none() => {
+                    registry = Some(RegistryCommand::List)
+                }
+                "run" if code_snippet.is_none() && registry.is_none() => {
+                    registry = Some(RegistryCommand::Run(value(&mut args, &arg)?))
+                }
+                _ if code_snippet.is_none() && registry.is_none() => code_snippet = Some(arg),
+                _ => return Err(format!("Unexpected argument: {arg}")),
+            }
+        }
+
+        Ok(Self {
+            lint,
+            list_capabilities,
+            startup_report,
+            code_snippet,
+            closure_file,
+            registry,
+            replace,
+            registry_dir,
+            stdin_format,
+            malformed_lines,
+            stdin_delimiter,
+            stream,
+            chunk_size,
+            timezone,
+            locale,
+            sandbox,
+            read_only,
+            deny_network,
+            deny_filesystem,
+            profiles,
+            allow_file,
+            deny,
+            root,
+            max_cpu,
+            max_memory,
+            timeout,
+            freeze_time,
+            secrets,
+            config,
+            lookups,
+            lookup_key,
+            datasets,
+            args: named_args,
+            dataset_max_bytes,
+            patterns,
+            #[cfg(feature = "geoip")]
+            geoip_db,
+            #[cfg(feature = "useragent")]
+            ua_regexes,
+            on_epipe,
+            expect,
+            post,
+            on_error,
+            suppress_duplicates,
+            dup_key,
+            report,
+            notify_on_failure,
+            notify_on_complete,
+            tees,
+            format,
+            rules,
+        })
+    }
+}
+
+fn value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("Missing value for {flag}"))
+}
@@ -0,0 +1,144 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::ThreadId;
+use std::time::Duration;
+#[cfg(not(unix))]
+use std::time::Instant;
+
+use nu_protocol::ast::{Block, PipelineElement};
+use nu_protocol::debugger::Debugger;
+use nu_protocol::engine::EngineState;
+use nu_protocol::ir::IrBlock;
+use nu_protocol::PipelineData;
+
+/// A [`Debugger`] that trips the engine's interrupt once evaluation has used more CPU time than
+/// its limit, which makes `loop`, `each` and every call fail with `Interrupted` from then on.
+///
+/// CPU time is sampled every so often as the evaluator enters blocks, pipeline elements and
+/// instructions, and only what the evaluating thread used between two samples counts, so waiting
+/// in `sleep` or on input doesn't. A single long-running command overshoots the limit until it
+/// returns or checks the interrupt itself. Where threads' CPU time can't be read, this falls back
+/// to wall-clock time.
+#[derive(Debug)]
+pub struct CpuBudget {
+    limit: Duration,
+    used: Duration,
+    /// The thread of the last sample and its CPU time then.
+    last: Option<(ThreadId, Duration)>,
+    /// Hooks left until the next sample.
+    countdown: u32,
+    interrupt: Arc<AtomicBool>,
+    exhausted: Arc<AtomicBool>,
+}
+
+/// Evaluator hooks between two samples of the CPU time, which takes a syscall.
+const SAMPLE_EVERY: u32 = 64;
+
+impl CpuBudget {
+    /// Sets `exhausted` along with `interrupt` once the limit is used up.
+    pub fn new(limit: Duration, interrupt: Arc<AtomicBool>, exhausted: Arc<AtomicBool>) -> Self {
+        Self {
+            limit,
+            used: Duration::ZERO,
+            last: None,
+            countdown: 0,
+            interrupt,
+            exhausted,
+        }
+    }
+
+    fn check(&mut self) {
+        if self.countdown > 0 {
+            self.countdown -= 1;
+            return;
+        }
+        self.countdown = SAMPLE_EVERY;
+        let thread = std::thread::current().id();
+        let now = thread_cpu_time();
+        if let Some((last_thread, last)) = self.last {
+            if last_thread == thread {
+                self.used += now.saturating_sub(last);
+            }
+        }
+        self.last = Some((thread, now));
+        if self.used > self.limit {
+            self.exhausted.store(true, Ordering::Relaxed);
+            self.interrupt.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+/// CPU time the calling thread has used so far.
+#[cfg(unix)]
+fn thread_cpu_time() -> Duration {
+    let mut time = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: `time` is a valid timespec for the call to write into.
+    unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut time) };
+    Duration::new(time.tv_sec as u64, time.tv_nsec as u32)
+}
+
+/// Wall-clock time since the first call, standing in for CPU time.
+#[cfg(not(unix))]
+fn thread_cpu_time() -> Duration {
+    static START: std::sync::OnceLock<Instant> = std::sync::OnceLock::new();
+    START.get_or_init(Instant::now).elapsed()
+}
+
+impl Debugger for CpuBudget {
+    fn activate(&mut self) {
+        self.used = Duration::ZERO;
+        self.last = None;
+        self.countdown = 0;
+    }
+
+    fn enter_block(&mut self, _engine_state: &EngineState, _block: &Block) {
+        self.check();
+    }
+
+    fn enter_element(&mut self, _engine_state: &EngineState, _element: &PipelineElement) {
+        self.check();
+    }
+
+    fn enter_instruction(
+        &mut self,
+        _engine_state: &EngineState,
+        _ir_block: &IrBlock,
+        _instruction_index: usize,
+        _registers: &[PipelineData],
+    ) {
+        self.check();
+    }
+}
+
+/// Trips an interrupt once a wall-clock timeout passes, from a thread of its own so it fires
+/// even while a command blocks. Arming it again cancels the previous countdown.
+#[derive(Debug, Default)]
+pub struct Watchdog {
+    cancel: Mutex<Option<Sender<()>>>,
+    fired: Arc<AtomicBool>,
+}
+
+impl Watchdog {
+    pub fn arm(&self, timeout: Duration, interrupt: Arc<AtomicBool>) {
+        let (cancel, cancelled) = mpsc::channel();
+        // Dropping the previous sender wakes its thread up with `Disconnected`.
+        *self.cancel.lock().expect("watchdog lock poisoned") = Some(cancel);
+        self.fired.store(false, Ordering::Relaxed);
+        let fired = self.fired.clone();
+        std::thread::spawn(move || {
+            if let Err(RecvTimeoutError::Timeout) = cancelled.recv_timeout(timeout) {
+                fired.store(true, Ordering::Relaxed);
+                interrupt.store(true, Ordering::Relaxed);
+            }
+        });
+    }
+
+    /// Whether the timeout passed since the last [`arm`](Self::arm).
+    pub fn fired(&self) -> bool {
+        self.fired.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,81 @@
+use serde::Serialize;
+
+use crate::Engine;
+
+const FILESYSTEM_READ: &[&str] = &[
+    "cd",
+    "du",
+    "glob",
+    "ls",
+    "nu-check",
+    "open",
+    "path exists",
+    "path expand",
+    "path type",
+    "watch",
+    "which",
+];
+const FILESYSTEM_WRITE: &[&str] = &[
+    "config reset",
+    "cp",
+    "mkdir",
+    "mktemp",
+    "mv",
+    "rm",
+    "save",
+    "start",
+    "touch",
+];
+const NETWORK: &[&str] = &[
+    "http",
+    "http delete",
+    "http get",
+    "http head",
+    "http options",
+    "http patch",
+    "http post",
+    "http put",
+    "port",
+];
+const PROCESS_SPAWN: &[&str] = &["exec", "kill", "run-external", "start", "ulimit"];
+
+/// The side-effectful commands an engine exposes, grouped by what they can touch, and whether it
+/// sees the host's environment. Commands that are missing or denied are left out.
+#[derive(Debug, Clone, Serialize)]
+pub struct Capabilities {
+    pub filesystem_read: Vec<&'static str>,
+    pub filesystem_write: Vec<&'static str>,
+    pub network: Vec<&'static str>,
+    pub process_spawn: Vec<&'static str>,
+    /// Whether `$env` was populated from the host; see [`Engine::has_host_env`].
+    pub host_env: bool,
+}
+
+impl Capabilities {
+    pub fn of(engine: &Engine) -> Self {
+        let available = |names: &[&'static str]| {
+            names
+                .iter()
+                .copied()
+                .filter(|name| engine.has_command(name))
+                .collect()
+        };
+        Self {
+            filesystem_read: available(FILESYSTEM_READ),
+            filesystem_write: available(FILESYSTEM_WRITE),
+            network: available(NETWORK),
+            process_spawn: available(PROCESS_SPAWN),
+            host_env: engine.has_host_env(),
+        }
+    }
+
+    /// Each capability with the commands that grant it, in report order.
+    pub fn entries(&self) -> [(&'static str, &[&'static str]); 4] {
+        [
+            ("filesystem_read", &self.filesystem_read),
+            ("filesystem_write", &self.filesystem_write),
+            ("network", &self.network),
+            ("process_spawn", &self.process_spawn),
+        ]
+    }
+}
@@ -0,0 +1,144 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset};
+use nu_engine::command_prelude::*;
+
+/// A clock that only moves when told to.
+///
+/// Registered with [`EngineBuilder::virtual_clock`](crate::EngineBuilder::virtual_clock), `sleep`
+/// advances it instead of blocking and `date now` reads from it, so code that waits runs
+/// instantly while still observing time pass. Clones share the same time.
+#[derive(Debug, Clone)]
+pub struct VirtualClock {
+    now: Arc<Mutex<DateTime<FixedOffset>>>,
+}
+
+impl VirtualClock {
+    pub fn new(start: DateTime<FixedOffset>) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(start)),
+        }
+    }
+
+    pub fn now(&self) -> DateTime<FixedOffset> {
+        *self.now.lock().expect("clock lock poisoned")
+    }
+
+    pub fn advance(&self, by: Duration) {
+        let mut now = self.now.lock().expect("clock lock poisoned");
+        if let Some(later) = chrono::Duration::from_std(by)
+            .ok()
+            .and_then(|by| now.checked_add_signed(by))
+        {
+            *now = later;
+        }
+    }
+}
+
+/// Where an engine's time comes from, for callers that need the time outside of evaluation,
+/// e.g. to stamp input as it arrives. See [`Engine::clock`](crate::Engine::clock).
+#[derive(Debug, Clone, Default)]
+pub enum Clock {
+    /// The system clock.
+    #[default]
+    System,
+    /// Always the same instant, from [`Engine::freeze_time`](crate::Engine::freeze_time).
+    Frozen(DateTime<FixedOffset>),
+    Virtual(VirtualClock),
+    /// No time at all, as in a deterministic engine without a frozen instant.
+    Denied,
+}
+
+impl Clock {
+    pub fn now(&self) -> Option<DateTime<FixedOffset>> {
+        match self {
+            Self::System => Some(chrono::Local::now().fixed_offset()),
+            Self::Frozen(now) => Some(*now),
+            Self::Virtual(clock) => Some(clock.now()),
+            Self::Denied => None,
+        }
+    }
+}
+
+/// `sleep` against a [`VirtualClock`].
+#[derive(Clone)]
+pub struct VirtualSleep {
+    clock: VirtualClock,
+}
+
+impl VirtualSleep {
+    pub fn new(clock: VirtualClock) -> Self {
+        Self { clock }
+    }
+}
+
+impl Command for VirtualSleep {
+    fn name(&self) -> &str {
+        "sleep"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("sleep")
+            .input_output_types(vec![(Type::Nothing, Type::Nothing)])
+            .required("duration", SyntaxShape::Duration, "Time to sleep.")
+            .rest("rest", SyntaxShape::Duration, "Additional time.")
+            .category(Category::Platform)
+    }
+
+    fn usage(&self) -> &str {
+        "Advance the virtual clock by a duration, without blocking."
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let duration: i64 = call.req(engine_state, stack, 0)?;
+        let rest: Vec<i64> = call.rest(engine_state, stack, 1)?;
+        let total = rest.into_iter().fold(duration, i64::saturating_add).max(0);
+        self.clock.advance(Duration::from_nanos(total as u64));
+        Ok(PipelineData::empty())
+    }
+}
+
+/// `date now` against a [`VirtualClock`].
+#[derive(Clone)]
+pub struct VirtualDateNow {
+    clock: VirtualClock,
+}
+
+impl VirtualDateNow {
+    pub fn new(clock: VirtualClock) -> Self {
+        Self { clock }
+    }
+}
+
+impl Command for VirtualDateNow {
+    fn name(&self) -> &str {
+        "date now"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("date now")
+            .input_output_types(vec![(Type::Nothing, Type::Date)])
+            .category(Category::Date)
+    }
+
+    fn usage(&self) -> &str {
+        "Get the current date of the virtual clock."
+    }
+
+    fn run(
+        &self,
+        _engine_state: &EngineState,
+        _stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        Ok(Value::date(self.clock.now(), call.head).into_pipeline_data())
+    }
+}
@@ -0,0 +1,72 @@
+use std::path::Path;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::notify::NotifySink;
+use crate::secrets::{SecretProvider, SecretStore};
+
+/// The default location of the config file, relative to the working directory.
+pub const CONFIG_FILE: &str = "mini-nu.toml";
+
+/// Settings read from `mini-nu.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub secrets: Option<SecretsConfig>,
+    pub notify: Option<NotifyConfig>,
+}
+
+/// The `[secrets]` table: a provider plus an optional cache lifetime in seconds.
+///
+/// ```toml
+/// [secrets]
+/// provider = "vault"
+/// addr = "https://vault.internal:8200"
+/// ttl = 300
+/// ```
+#[derive(Debug, Deserialize)]
+pub struct SecretsConfig {
+    #[serde(flatten)]
+    pub provider: SecretProvider,
+    pub ttl: Option<u64>,
+}
+
+impl SecretsConfig {
+    pub fn store(&self) -> SecretStore {
+        let store = SecretStore::new(self.provider.clone());
+        match self.ttl {
+            Some(ttl) => store.with_ttl(Duration::from_secs(ttl)),
+            None => store,
+        }
+    }
+}
+
+/// The `[notify]` table: a sink for `--notify`, plus an optional closure turning the run's
+/// summary record into the message.
+///
+/// ```toml
+/// [notify]
+/// sink = "cmd"
+/// program = "sendmail"
+/// args = ["ops@example.com"]
+/// template = '{|run| $"Subject: batch ($run.status)\n\n($run.results) results" }'
+/// ```
+#[derive(Debug, Deserialize)]
+pub struct NotifyConfig {
+    #[serde(flatten)]
+    pub sink: NotifySink,
+    pub template: Option<String>,
+}
+
+impl Config {
+    /// Load `path`, or the default config if it doesn't exist.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => toml::from_str(&contents)
+                .map_err(|err| format!("Invalid config {}: {err}", path.display())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(format!("Can't read config {}: {err}", path.display())),
+        }
+    }
+}
@@ -0,0 +1,365 @@
+use chrono::{DateTime, FixedOffset, Utc};
+use nu_engine::command_prelude::*;
+use nu_protocol::CustomValue;
+use polars::frame::DataFrame;
+use polars::prelude::{AnyValue, PolarsError, SortMultipleOptions, TimeUnit};
+use serde::{Deserialize, Serialize};
+
+/// The type name frames go by in signatures and errors.
+const DATAFRAME: &str = "dataframe";
+
+/// A polars [`DataFrame`] carried through a pipeline as a custom value.
+///
+/// Closures can pass it along, run the `df` commands from [`commands`] on it, or materialize it
+/// into a table; the host gets the frame back without converting it to rows and back.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataFrameValue {
+    df: DataFrame,
+}
+
+impl DataFrameValue {
+    pub fn new(df: DataFrame) -> Self {
+        Self { df }
+    }
+
+    pub fn into_value(self, span: Span) -> Value {
+        Value::custom(Box::new(self), span)
+    }
+
+    pub fn try_from_value(value: Value) -> Result<DataFrame, ShellError> {
+        let span = value.span();
+        match value {
+            Value::Custom { val, .. } => match val.as_any().downcast_ref::<Self>() {
+                Some(frame) => Ok(frame.df.clone()),
+                None => Err(ShellError::CantConvert {
+                    to_type: DATAFRAME.into(),
+                    from_type: val.type_name(),
+                    span,
+                    help: None,
+                }),
+            },
+            other => Err(ShellError::CantConvert {
+                to_type: DATAFRAME.into(),
+                from_type: other.get_type().to_string(),
+                span,
+                help: None,
+            }),
+        }
+    }
+}
+
+#[typetag::serde]
+impl CustomValue for DataFrameValue {
+    fn clone_value(&self, span: Span) -> Value {
+        self.clone().into_value(span)
+    }
+
+    fn type_name(&self) -> String {
+        DATAFRAME.into()
+    }
+
+    /// A table with a record per row, each cell converted by its column's dtype.
+    fn to_base_value(&self, span: Span) -> Result<Value, ShellError> {
+        let columns: Vec<(String, Vec<Value>)> = self
+            .df
+            .get_columns()
+            .iter()
+            .map(|column| {
+                let series = column.as_materialized_series().rechunk();
+                let cells = series
+                    .iter()
+                    .map(|cell| cell_to_value(cell, span))
+                    .collect();
+                (column.name().to_string(), cells)
+            })
+            .collect();
+        let rows = (0..self.df.height())
+            .map(|i| {
+                let record = columns
+                    .iter()
+                    .map(|(name, cells)| (name.clone(), cells[i].clone()))
+                    .collect();
+                Value::record(record, span)
+            })
+            .collect();
+        Ok(Value::list(rows, span))
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+/// The nu value for a single cell. Unsigned integers past `i64::MAX` become floats; dtypes nu
+/// has no counterpart for are shown as strings.
+fn cell_to_value(cell: AnyValue, span: Span) -> Value {
+    match cell {
+        AnyValue::Null => Value::nothing(span),
+        AnyValue::Boolean(b) => Value::bool(b, span),
+        AnyValue::String(s) => Value::string(s, span),
+        AnyValue::StringOwned(s) => Value::string(s.as_str(), span),
+        AnyValue::Int8(n) => Value::int(n.into(), span),
+        AnyValue::Int16(n) => Value::int(n.into(), span),
+        AnyValue::Int32(n) => Value::int(n.into(), span),
+        AnyValue::Int64(n) => Value::int(n, span),
+        AnyValue::UInt8(n) => Value::int(n.into(), span),
+        AnyValue::UInt16(n) => Value::int(n.into(), span),
+        AnyValue::UInt32(n) => Value::int(n.into(), span),
+        AnyValue::UInt64(n) => match i64::try_from(n) {
+            Ok(n) => Value::int(n, span),
+            Err(_) => Value::float(n as f64, span),
+        },
+        AnyValue::Float32(n) => Value::float(n.into(), span),
+        AnyValue::Float64(n) => Value::float(n, span),
+        AnyValue::Date(days) => DateTime::from_timestamp(i64::from(days) * 86_400, 0).map_or_else(
+            || Value::nothing(span),
+            |date| Value::date(date.fixed_offset(), span),
+        ),
+        AnyValue::Datetime(t, unit, tz) => datetime(t, unit, tz.map(|tz| tz.as_str()), span),
+        AnyValue::DatetimeOwned(t, unit, tz) => {
+            datetime(t, unit, tz.as_deref().map(|tz| tz.as_str()), span)
+        }
+        AnyValue::Duration(t, unit) => Value::duration(nanos(t, unit), span),
+        AnyValue::List(series) => Value::list(
+            series
+                .rechunk()
+                .iter()
+                .map(|cell| cell_to_value(cell, span))
+                .collect(),
+            span,
+        ),
+        AnyValue::Binary(bytes) => Value::binary(bytes, span),
+        AnyValue::BinaryOwned(bytes) => Value::binary(bytes, span),
+        other => Value::string(other.to_string(), span),
+    }
+}
+
+/// `t` in `unit` since the epoch, in `tz` if it's a known timezone and UTC otherwise.
+fn datetime(t: i64, unit: TimeUnit, tz: Option<&str>, span: Span) -> Value {
+    let utc = DateTime::<Utc>::from_timestamp_nanos(nanos(t, unit));
+    let date: DateTime<FixedOffset> = match tz.and_then(|tz| tz.parse::<chrono_tz::Tz>().ok()) {
+        Some(tz) => utc.with_timezone(&tz).fixed_offset(),
+        None => utc.fixed_offset(),
+    };
+    Value::date(date, span)
+}
+
+fn nanos(t: i64, unit: TimeUnit) -> i64 {
+    match unit {
+        TimeUnit::Nanoseconds => t,
+        TimeUnit::Microseconds => t.saturating_mul(1_000),
+        TimeUnit::Milliseconds => t.saturating_mul(1_000_000),
+    }
+}
+
+/// The `df` commands, each taking a frame and returning a new one.
+pub fn commands() -> Vec<Box<dyn Command>> {
+    vec![
+        Box::new(DfHead),
+        Box::new(DfTail),
+        Box::new(DfSelect),
+        Box::new(DfDrop),
+        Box::new(DfSortBy),
+    ]
+}
+
+fn frame_signature(name: &str) -> Signature {
+    let frame = Type::Custom(DATAFRAME.into());
+    Signature::build(name)
+        .input_output_types(vec![(frame.clone(), frame)])
+        .category(Category::Custom(DATAFRAME.into()))
+}
+
+fn input_frame(input: PipelineData, span: Span) -> Result<DataFrame, ShellError> {
+    DataFrameValue::try_from_value(input.into_value(span)?)
+}
+
+fn output_frame(df: DataFrame, span: Span) -> PipelineData {
+    DataFrameValue::new(df)
+        .into_value(span)
+        .into_pipeline_data()
+}
+
+fn polars_error(err: PolarsError, span: Span) -> ShellError {
+    ShellError::GenericError {
+        error: "Dataframe operation failed".into(),
+        msg: err.to_string(),
+        span: Some(span),
+        help: None,
+        inner: vec![],
+    }
+}
+
+/// `df head [n]`: the first `n` rows, 5 by default.
+#[derive(Clone)]
+pub struct DfHead;
+
+impl Command for DfHead {
+    fn name(&self) -> &str {
+        "df head"
+    }
+
+    fn signature(&self) -> Signature {
+        frame_signature("df head").optional(
+            "n",
+            SyntaxShape::Int,
+            "Number of rows, 5 if not given.",
+        )
+    }
+
+    fn usage(&self) -> &str {
+        "The first rows of a dataframe."
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let n: Option<usize> = call.opt(engine_state, stack, 0)?;
+        let df = input_frame(input, call.head)?;
+        Ok(output_frame(df.head(Some(n.unwrap_or(5))), call.head))
+    }
+}
+
+/// `df tail [n]`: the last `n` rows, 5 by default.
+#[derive(Clone)]
+pub struct DfTail;
+
+impl Command for DfTail {
+    fn name(&self) -> &str {
+        "df tail"
+    }
+
+    fn signature(&self) -> Signature {
+        frame_signature("df tail").optional(
+            "n",
+            SyntaxShape::Int,
+            "Number of rows, 5 if not given.",
+        )
+    }
+
+    fn usage(&self) -> &str {
+        "The last rows of a dataframe."
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let n: Option<usize> = call.opt(engine_state, stack, 0)?;
+        let df = input_frame(input, call.head)?;
+        Ok(output_frame(df.tail(Some(n.unwrap_or(5))), call.head))
+    }
+}
+
+/// `df select ...columns`: only the given columns, in that order.
+#[derive(Clone)]
+pub struct DfSelect;
+
+impl Command for DfSelect {
+    fn name(&self) -> &str {
+        "df select"
+    }
+
+    fn signature(&self) -> Signature {
+        frame_signature("df select").rest("columns", SyntaxShape::String, "Columns to keep.")
+    }
+
+    fn usage(&self) -> &str {
+        "Keep only the given columns of a dataframe."
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let columns: Vec<String> = call.rest(engine_state, stack, 0)?;
+        let df = input_frame(input, call.head)?;
+        let df = df
+            .select(columns)
+            .map_err(|err| polars_error(err, call.head))?;
+        Ok(output_frame(df, call.head))
+    }
+}
+
+/// `df drop ...columns`: every column but the given ones.
+#[derive(Clone)]
+pub struct DfDrop;
+
+impl Command for DfDrop {
+    fn name(&self) -> &str {
+        "df drop"
+    }
+
+    fn signature(&self) -> Signature {
+        frame_signature("df drop").rest("columns", SyntaxShape::String, "Columns to drop.")
+    }
+
+    fn usage(&self) -> &str {
+        "Drop the given columns from a dataframe."
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let columns: Vec<String> = call.rest(engine_state, stack, 0)?;
+        let df = input_frame(input, call.head)?;
+        Ok(output_frame(df.drop_many(columns), call.head))
+    }
+}
+
+/// `df sort-by column [--reverse]`: rows ordered by one column, nulls last.
+#[derive(Clone)]
+pub struct DfSortBy;
+
+impl Command for DfSortBy {
+    fn name(&self) -> &str {
+        "df sort-by"
+    }
+
+    fn signature(&self) -> Signature {
+        frame_signature("df sort-by")
+            .required("column", SyntaxShape::String, "Column to sort by.")
+            .switch("reverse", "Sort in descending order.", Some('r'))
+    }
+
+    fn usage(&self) -> &str {
+        "Sort the rows of a dataframe by a column."
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let column: String = call.req(engine_state, stack, 0)?;
+        let reverse = call.has_flag(engine_state, stack, "reverse")?;
+        let df = input_frame(input, call.head)?;
+        let options = SortMultipleOptions::default()
+            .with_order_descending(reverse)
+            .with_nulls_last(true);
+        let df = df
+            .sort([column], options)
+            .map_err(|err| polars_error(err, call.head))?;
+        Ok(output_frame(df, call.head))
+    }
+}
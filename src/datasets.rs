@@ -0,0 +1,81 @@
+use std::io::Read;
+use std::path::Path;
+
+use nu_protocol::{Record, Span, Value};
+
+use crate::json::json_to_value;
+
+/// Load a data file into a value, by extension: `.csv` becomes a table of strings and `.json`
+/// whatever it holds. Files over `max_bytes` are refused rather than read.
+pub fn load(path: &Path, max_bytes: Option<u64>) -> Result<Value, String> {
+    if let Some(max_bytes) = max_bytes {
+        let size = std::fs::metadata(path)
+            .map_err(|err| format!("Can't read {}: {err}", path.display()))?
+            .len();
+        if size > max_bytes {
+            return Err(format!(
+                "{} is {size} bytes, over the {max_bytes} byte limit",
+                path.display()
+            ));
+        }
+    }
+
+    let span = Span::unknown();
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("csv") => Ok(Value::list(
+            read_csv(path)?
+                .into_iter()
+                .map(|row| Value::record(row, span))
+                .collect(),
+            span,
+        )),
+        Some("json") => {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|err| format!("Can't read {}: {err}", path.display()))?;
+            let json = serde_json::from_str(&contents)
+                .map_err(|err| format!("Invalid JSON {}: {err}", path.display()))?;
+            Ok(json_to_value(json, span))
+        }
+        _ => Err(format!(
+            "Unsupported dataset {}, expected a .csv or .json file",
+            path.display()
+        )),
+    }
+}
+
+/// Read the rows of a CSV file with a header line, keeping every field as a string.
+pub fn read_csv(path: &Path) -> Result<Vec<Record>, String> {
+    let file =
+        std::fs::File::open(path).map_err(|err| format!("Can't read {}: {err}", path.display()))?;
+    parse_csv(file, b',', &path.display().to_string())
+}
+
+/// Read CSV rows with a header line from `reader`, with fields split on `delimiter`; `source`
+/// names it in errors.
+pub fn parse_csv(reader: impl Read, delimiter: u8, source: &str) -> Result<Vec<Record>, String> {
+    csv_rows(reader, delimiter, source)?.collect()
+}
+
+/// [`parse_csv`], a row at a time.
+pub fn csv_rows<R: Read>(
+    reader: R,
+    delimiter: u8,
+    source: &str,
+) -> Result<impl Iterator<Item = Result<Record, String>>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .from_reader(reader);
+    let headers = reader
+        .headers()
+        .map_err(|err| format!("Invalid CSV {source}: {err}"))?
+        .clone();
+    let source = source.to_string();
+    Ok(reader.into_records().map(move |row| {
+        let row = row.map_err(|err| format!("Invalid CSV {source}: {err}"))?;
+        Ok(headers
+            .iter()
+            .zip(row.iter())
+            .map(|(col, val)| (col.to_string(), Value::string(val, Span::unknown())))
+            .collect())
+    }))
+}
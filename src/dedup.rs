@@ -0,0 +1,56 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Keys remembered at most, so an endless stream of distinct results can't grow it unbounded.
+pub const CAPACITY: usize = 10_000;
+
+/// Remembers which keys were written recently, to drop repeats within a time window. Past
+/// [`CAPACITY`] keys, the one written longest ago is forgotten first.
+pub struct DuplicateWindow {
+    window: Duration,
+    /// When each remembered key was last written.
+    written: HashMap<String, Instant>,
+    /// Writes in order; entries superseded by a later write of the same key are skipped.
+    order: VecDeque<(String, Instant)>,
+}
+
+impl DuplicateWindow {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            written: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Whether `key` was written within the window; if not, it counts as written now. A repeat
+    /// doesn't refresh the time, so a key seen continuously still gets through once per window.
+    pub fn is_duplicate(&mut self, key: String) -> bool {
+        let now = Instant::now();
+        while self
+            .order
+            .front()
+            .is_some_and(|(_, at)| now.duration_since(*at) >= self.window)
+        {
+            self.forget_oldest();
+        }
+
+        if self.written.contains_key(&key) {
+            return true;
+        }
+        while self.written.len() >= CAPACITY {
+            self.forget_oldest();
+        }
+        self.written.insert(key.clone(), now);
+        self.order.push_back((key, now));
+        false
+    }
+
+    fn forget_oldest(&mut self) {
+        if let Some((oldest, at)) = self.order.pop_front() {
+            if self.written.get(&oldest) == Some(&at) {
+                self.written.remove(&oldest);
+            }
+        }
+    }
+}
@@ -0,0 +1,984 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset};
+use nu_cli::{add_cli_context, gather_parent_env_vars};
+use nu_cmd_lang::create_default_context;
+use nu_command::add_shell_command_context;
+use nu_engine::{get_eval_block_with_early_return, ClosureEvalOnce};
+use nu_parser::{flatten_block, FlatShape};
+use nu_protocol::ast::{Block, Expr, PipelineRedirection, RedirectionTarget};
+use nu_protocol::debugger::NoopDebugger;
+use nu_protocol::engine::{Closure, Command, EngineState, Stack, StateWorkingSet};
+use nu_protocol::{
+    Category, PipelineData, ShellError, Signals, Span, Value, VarId, NU_VARIABLE_ID,
+};
+use regex::Regex;
+
+use crate::budget::{CpuBudget, Watchdog};
+use crate::capabilities::Capabilities;
+use crate::clock::{Clock, VirtualClock, VirtualDateNow, VirtualSleep};
+use crate::extract::Extract;
+use crate::jail::Jailed;
+use crate::lookup::{Lookup, LookupTable};
+use crate::memory;
+use crate::profiles::Profile;
+use crate::sandbox::{
+    denied_calls, denied_error, AbsoluteIntoDatetime, Denied, FrozenDateNow, NoDeviceFiles,
+    DENIED_CATEGORY, FILESYSTEM_COMMANDS, FILESYSTEM_WRITE_COMMANDS, NETWORK_COMMANDS,
+    NONDETERMINISTIC_COMMANDS,
+};
+use crate::secrets::{SecretGet, SecretReveal, SecretStore};
+use crate::startup::StartupReport;
+use crate::vfs::{VfsLs, VfsOpen, VfsProvider, VfsSave};
+
+/// A Nushell engine with the default language and shell commands loaded.
+pub struct Engine {
+    pub state: EngineState,
+    /// Reject snippets that redirect output into a file (`o> file`), which writes without `save`.
+    deny_file_redirection: bool,
+    /// Reject snippets that load other files while parsing, with `source` or `use`.
+    deny_file_loading: bool,
+    /// Reject snippets that read `$nu`, which describes the host and the running process.
+    deny_nu_variable: bool,
+    /// See [`Engine::has_host_env`].
+    host_env: bool,
+    /// Hooked up to the engine's [`Signals`]; setting it interrupts evaluation.
+    interrupt: Arc<AtomicBool>,
+    /// See [`Engine::limit_cpu`].
+    cpu_limit: Option<Duration>,
+    /// See [`Engine::limit_memory`].
+    memory_limit: Option<usize>,
+    /// See [`Engine::timeout`].
+    timeout: Option<Duration>,
+    watchdog: Watchdog,
+    /// Set by the [`CpuBudget`] once it's used up.
+    cpu_exhausted: Arc<AtomicBool>,
+    /// Whether the limits are armed; see [`Engine::rearm_limits`].
+    armed: AtomicBool,
+    /// See [`Engine::clock`].
+    clock: Clock,
+    /// Variables from [`Engine::add_constant`], put on every evaluation's stack.
+    constants: Vec<(VarId, Value)>,
+    /// Blocks already parsed, by file name and source. Registering commands or constants clears
+    /// it, as cached blocks still point at whatever their names resolved to when they were parsed.
+    parsed: HashMap<(Option<String>, String), Arc<Block>>,
+    /// See [`Engine::startup_report`].
+    startup: StartupReport,
+}
+
+/// The `error` of the [`ShellError::GenericError`]s the engine raises while parsing, so
+/// [`SandboxError::from_parse`](crate::SandboxError::from_parse) can tell them apart.
+pub(crate) const PARSE_ERROR: &str = "Parse error";
+pub(crate) const POLICY_ERROR: &str = "Not allowed by the engine's policy";
+
+/// The `error` of [`Engine::limit_exceeded`] for each [`Limit`].
+const TIMEOUT_ERROR: &str = "Evaluation timed out";
+const CPU_LIMIT_ERROR: &str = "CPU time limit exceeded";
+const MEMORY_LIMIT_ERROR: &str = "Memory limit exceeded";
+
+/// A limit that stopped an evaluation; see [`Engine::exceeded_limit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// See [`Engine::limit_cpu`].
+    Cpu,
+    /// See [`Engine::timeout`].
+    Timeout,
+    /// See [`Engine::limit_memory`].
+    Memory,
+}
+
+/// Keywords that read a file while the snippet is being parsed.
+const FILE_LOADING_KEYWORDS: &[&str] =
+    &["source", "source-env", "use", "export use", "overlay use"];
+
+/// Composes an [`Engine`] from the language core plus whichever command sets are asked for.
+///
+/// A full engine, as [`Engine::new`] builds it:
+///
+/// ```no_run
+/// # use mini_nu::engine::EngineBuilder;
+/// let engine = EngineBuilder::new().with_shell_commands().with_env().build()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// A sandbox that can only filter its input:
+///
+/// ```no_run
+/// # use mini_nu::engine::EngineBuilder;
+/// let engine = EngineBuilder::new()
+///     .with_custom(Box::new(nu_command::Where))
+///     .with_custom(Box::new(nu_command::Each))
+///     .with_custom(Box::new(nu_command::Length))
+///     .build()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Default)]
+pub struct EngineBuilder {
+    shell_commands: bool,
+    cli_context: bool,
+    env: bool,
+    custom: Vec<Box<dyn Command>>,
+    read_only: bool,
+    deny_network: bool,
+    deny_filesystem: bool,
+    deterministic: bool,
+    frozen_now: Option<DateTime<FixedOffset>>,
+    virtual_clock: Option<VirtualClock>,
+    allowed: Option<Vec<String>>,
+    denied: Vec<String>,
+    cpu_limit: Option<Duration>,
+    memory_limit: Option<usize>,
+    timeout: Option<Duration>,
+    jail: Option<PathBuf>,
+    vfs: Option<Arc<dyn VfsProvider>>,
+}
+
+impl EngineBuilder {
+    /// Start from the language core (`nu-cmd-lang`) only.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the `nu-command` shell commands: filesystem, formats, strings, network and the rest.
+    pub fn with_shell_commands(mut self) -> Self {
+        self.shell_commands = true;
+        self
+    }
+
+    /// Add the REPL-oriented commands from `nu-cli`, like `commandline` and `history`.
+    pub fn with_cli_context(mut self) -> Self {
+        self.cli_context = true;
+        self
+    }
+
+    /// Populate `$env` from the current process and working directory.
+    pub fn with_env(mut self) -> Self {
+        self.env = true;
+        self
+    }
+
+    /// Register `decl` after the command sets, shadowing any command with the same name.
+    pub fn with_custom(mut self, decl: Box<dyn Command>) -> Self {
+        self.custom.push(decl);
+        self
+    }
+
+    /// Register the commands of `profile`, e.g. on top of the bare language core for a sandbox.
+    pub fn with_profile(mut self, profile: Profile) -> Self {
+        self.custom.extend(profile.commands());
+        self
+    }
+
+    /// Register the `df` commands working on frames passed in with
+    /// [`Engine::eval_with_dataframe`].
+    #[cfg(feature = "polars")]
+    pub fn with_dataframe_commands(mut self) -> Self {
+        self.custom.extend(crate::dataframe::commands());
+        self
+    }
+
+    /// See [`Engine::allow_only`]. Repeated calls add to the allowlist.
+    pub fn allow(mut self, names: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.allowed
+            .get_or_insert_with(Vec::new)
+            .extend(names.into_iter().map(Into::into));
+        self
+    }
+
+    /// Deny each of `names`, on top of whatever else is denied.
+    pub fn deny(mut self, names: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.denied.extend(names.into_iter().map(Into::into));
+        self
+    }
+
+    /// See [`Engine::make_read_only`].
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// See [`Engine::deny_network`].
+    pub fn deny_network(mut self) -> Self {
+        self.deny_network = true;
+        self
+    }
+
+    /// See [`Engine::deny_filesystem`].
+    pub fn deny_filesystem(mut self) -> Self {
+        self.deny_filesystem = true;
+        self
+    }
+
+    /// See [`Engine::make_deterministic`]. The host's `$env` isn't gathered even with
+    /// [`EngineBuilder::with_env`], and unless the engine is jailed or on a vfs, filesystem access
+    /// is denied as with [`EngineBuilder::deny_filesystem`], since the host's files could differ
+    /// from one run to the next.
+    pub fn deterministic(mut self) -> Self {
+        self.deterministic = true;
+        self
+    }
+
+    /// See [`Engine::freeze_time`].
+    pub fn freeze_time(mut self, now: DateTime<FixedOffset>) -> Self {
+        self.frozen_now = Some(now);
+        self
+    }
+
+    /// See [`Engine::use_virtual_clock`].
+    pub fn virtual_clock(mut self, clock: VirtualClock) -> Self {
+        self.virtual_clock = Some(clock);
+        self
+    }
+
+    /// See [`Engine::limit_cpu`].
+    pub fn max_cpu(mut self, limit: Duration) -> Self {
+        self.cpu_limit = Some(limit);
+        self
+    }
+
+    /// See [`Engine::limit_memory`].
+    pub fn max_memory(mut self, bytes: usize) -> Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
+    /// See [`Engine::timeout`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// See [`Engine::jail`].
+    pub fn jail(mut self, root: impl Into<PathBuf>) -> Self {
+        self.jail = Some(root.into());
+        self
+    }
+
+    /// See [`Engine::use_vfs`].
+    pub fn vfs(mut self, vfs: Arc<dyn VfsProvider>) -> Self {
+        self.vfs = Some(vfs);
+        self
+    }
+
+    pub fn build(self) -> Result<Engine, Box<dyn std::error::Error>> {
+        let mut startup = StartupReport::default();
+        let mut engine_state = startup.record("default context", create_default_context);
+        if self.shell_commands {
+            engine_state =
+                startup.record("shell commands", || add_shell_command_context(engine_state));
+        }
+        if self.cli_context {
+            engine_state = startup.record("cli context", || add_cli_context(engine_state));
+        }
+        let host_env = self.env && !self.deterministic;
+        if host_env {
+            let init_cwd = std::env::current_dir()?;
+            startup.record("env", || {
+                gather_parent_env_vars(&mut engine_state, init_cwd.as_ref())
+            });
+        }
+
+        let interrupt = Arc::new(AtomicBool::new(false));
+        engine_state.set_signals(Signals::new(interrupt.clone()));
+
+        let mut engine = Engine {
+            state: engine_state,
+            deny_file_redirection: false,
+            deny_file_loading: false,
+            deny_nu_variable: false,
+            host_env,
+            interrupt,
+            cpu_limit: self.cpu_limit,
+            memory_limit: self.memory_limit,
+            timeout: self.timeout,
+            watchdog: Watchdog::default(),
+            cpu_exhausted: Default::default(),
+            armed: AtomicBool::new(false),
+            clock: Clock::default(),
+            constants: vec![],
+            parsed: HashMap::new(),
+            startup: StartupReport::default(),
+        };
+        startup.record("custom decls", || engine.add_commands(self.custom))?;
+        startup.record("policy", || -> Result<(), ShellError> {
+            let confined = self.jail.is_some() || self.vfs.is_some();
+            // The jail and the vfs bring back commands of their own, so they go first for the
+            // denials below to still take them away again.
+            if let Some(root) = &self.jail {
+                engine.jail(root)?;
+            }
+            if let Some(vfs) = self.vfs {
+                engine.use_vfs(vfs)?;
+            }
+            if let Some(allowed) = &self.allowed {
+                let allowed: Vec<&str> = allowed.iter().map(String::as_str).collect();
+                engine.allow_only(&allowed)?;
+            }
+            if !self.denied.is_empty() {
+                let denied: Vec<&str> = self.denied.iter().map(String::as_str).collect();
+                engine.deny(&denied, "Denied by the engine's configuration")?;
+            }
+            if self.read_only {
+                engine.make_read_only()?;
+            }
+            if self.deny_network {
+                engine.deny_network()?;
+            }
+            if self.deny_filesystem || (self.deterministic && !confined) {
+                engine.deny_filesystem()?;
+            }
+            if self.deterministic {
+                engine.make_deterministic(self.frozen_now)?;
+            } else if let Some(now) = self.frozen_now {
+                engine.freeze_time(now)?;
+            }
+            if let Some(clock) = self.virtual_clock {
+                engine.use_virtual_clock(clock)?;
+            }
+            Ok(())
+        })?;
+        startup.decls = engine.state.num_decls();
+        engine.startup = startup;
+        Ok(engine)
+    }
+}
+
+impl Engine {
+    /// Create an engine with the language core and shell commands, and `$env` populated from the
+    /// current process.
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        EngineBuilder::new()
+            .with_shell_commands()
+            .with_env()
+            .build()
+    }
+
+    /// A copy of this engine that can evaluate alongside it: same commands, constants, policy and
+    /// limits, with its own interrupt and debugger so one copy's budget doesn't stop another.
+    /// Closures parsed by either engine before the fork work in both.
+    pub fn fork(&self) -> Self {
+        let mut state = self.state.clone();
+        let interrupt = Arc::new(AtomicBool::new(false));
+        state.set_signals(Signals::new(interrupt.clone()));
+        state.debugger = Arc::new(Mutex::new(Box::new(NoopDebugger)));
+        Self {
+            state,
+            deny_file_redirection: self.deny_file_redirection,
+            deny_file_loading: self.deny_file_loading,
+            deny_nu_variable: self.deny_nu_variable,
+            host_env: self.host_env,
+            interrupt,
+            cpu_limit: self.cpu_limit,
+            memory_limit: self.memory_limit,
+            timeout: self.timeout,
+            watchdog: Watchdog::default(),
+            cpu_exhausted: Default::default(),
+            armed: AtomicBool::new(false),
+            clock: self.clock.clone(),
+            constants: self.constants.clone(),
+            parsed: self.parsed.clone(),
+            startup: self.startup.clone(),
+        }
+    }
+
+    /// Register `decl`, shadowing any existing command with the same name.
+    pub fn add_command(&mut self, decl: Box<dyn Command>) -> Result<(), ShellError> {
+        self.add_commands([decl])
+    }
+
+    /// Register several commands in a single delta.
+    pub fn add_commands(
+        &mut self,
+        decls: impl IntoIterator<Item = Box<dyn Command>>,
+    ) -> Result<(), ShellError> {
+        let mut working_set = StateWorkingSet::new(&self.state);
+        for decl in decls {
+            working_set.add_decl(decl);
+        }
+        self.parsed.clear();
+        self.state.merge_delta(working_set.render())
+    }
+
+    /// Deny every command in [`NONDETERMINISTIC_COMMANDS`], so the same snippet and input always
+    /// produce the same output. `into datetime` loses relative dates and `open` device files (see
+    /// [`AbsoluteIntoDatetime`] and [`NoDeviceFiles`]), and reading `$nu` is rejected at parse
+    /// time. With `frozen_now`, `date now` returns that instant instead of being denied.
+    ///
+    /// `$env` and the filesystem are left as they are; [`EngineBuilder::deterministic`] takes
+    /// those away too.
+    pub fn make_deterministic(
+        &mut self,
+        frozen_now: Option<DateTime<FixedOffset>>,
+    ) -> Result<(), ShellError> {
+        self.deny(
+            NONDETERMINISTIC_COMMANDS,
+            "Nondeterministic commands are disabled",
+        )?;
+        self.refuse_relative_dates()?;
+        self.deny_nu_variable = true;
+        if let Some(decl_id) = self
+            .state
+            .find_decl(b"open", &[])
+            .filter(|_| self.has_command("open"))
+        {
+            let open = self.state.get_decl(decl_id).clone_box();
+            self.add_command(Box::new(NoDeviceFiles::new(open)))?;
+        }
+        match frozen_now {
+            Some(now) => self.freeze_time(now),
+            None => {
+                self.clock = Clock::Denied;
+                Ok(())
+            }
+        }
+    }
+
+    /// Deny every command in [`FILESYSTEM_WRITE_COMMANDS`], leaving `open`, `ls`, `glob` and the
+    /// other readers in place.
+    /// File redirections are rejected at parse time too.
+    pub fn make_read_only(&mut self) -> Result<(), ShellError> {
+        self.deny_file_redirection = true;
+        self.deny(FILESYSTEM_WRITE_COMMANDS, "The engine is read-only")
+    }
+
+    /// Deny every command in [`NETWORK_COMMANDS`].
+    pub fn deny_network(&mut self) -> Result<(), ShellError> {
+        self.deny(NETWORK_COMMANDS, "Network access is disabled")
+    }
+
+    /// Deny every command in [`FILESYSTEM_COMMANDS`]. File redirections and `source`/`use` of
+    /// files are rejected at parse time too.
+    pub fn deny_filesystem(&mut self) -> Result<(), ShellError> {
+        self.deny_file_redirection = true;
+        self.deny_file_loading = true;
+        self.deny(FILESYSTEM_COMMANDS, "Filesystem access is disabled")
+    }
+
+    /// Deny every command outside the language core that isn't in `allowed`.
+    pub fn allow_only(&mut self, allowed: &[&str]) -> Result<(), ShellError> {
+        let core: HashSet<Vec<u8>> = create_default_context()
+            .get_decls_sorted(true)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        let denied: Vec<String> = self
+            .state
+            .get_decls_sorted(true)
+            .into_iter()
+            .filter(|(name, _)| !core.contains(name))
+            .map(|(name, _)| String::from_utf8_lossy(&name).into_owned())
+            .filter(|name| !allowed.contains(&name.as_str()))
+            .collect();
+        let denied: Vec<&str> = denied.iter().map(String::as_str).collect();
+        self.deny(&denied, "Not in the engine's allowlist")
+    }
+
+    /// Deny filesystem access as [`Engine::deny_filesystem`] does, then bring back `open`, `ls` and
+    /// `save` limited to paths under `root`, which also becomes the working directory. See
+    /// [`Jailed`].
+    pub fn jail(&mut self, root: &Path) -> Result<(), ShellError> {
+        let root = root
+            .canonicalize()
+            .map_err(|err| ShellError::GenericError {
+                error: format!("Can't use {} as the filesystem root", root.display()),
+                msg: err.to_string(),
+                span: None,
+                help: None,
+                inner: vec![],
+            })?;
+        self.deny_filesystem()?;
+        self.add_commands([
+            Box::new(Jailed::new(Box::new(nu_command::Open), root.clone(), &[]).with_path_input())
+                as Box<dyn Command>,
+            Box::new(Jailed::new(Box::new(nu_command::Ls), root.clone(), &[])),
+            Box::new(Jailed::new(
+                Box::new(nu_command::Save),
+                root.clone(),
+                &["stderr"],
+            )),
+        ])?;
+        self.state.add_env_var(
+            "PWD".into(),
+            Value::string(root.to_string_lossy(), Span::unknown()),
+        );
+        Ok(())
+    }
+
+    /// Deny filesystem access as [`Engine::deny_filesystem`] does, then bring back `open`, `save`
+    /// and `ls` working on `vfs` instead of the real filesystem.
+    pub fn use_vfs(&mut self, vfs: Arc<dyn VfsProvider>) -> Result<(), ShellError> {
+        self.deny_filesystem()?;
+        self.add_commands([
+            Box::new(VfsOpen::new(vfs.clone())) as Box<dyn Command>,
+            Box::new(VfsSave::new(vfs.clone())),
+            Box::new(VfsLs::new(vfs)),
+        ])
+    }
+
+    /// Whether `$env` was populated from the host's environment when the engine was built.
+    pub fn has_host_env(&self) -> bool {
+        self.host_env
+    }
+
+    /// Whether `name` is registered and not denied.
+    pub fn has_command(&self, name: &str) -> bool {
+        self.state
+            .find_decl(name.as_bytes(), &[])
+            .is_some_and(|decl_id| {
+                self.state.get_decl(decl_id).signature().category
+                    != Category::Custom(DENIED_CATEGORY.into())
+            })
+    }
+
+    /// Replace each of `names` with a stub that fails with `reason` when run.
+    pub fn deny(&mut self, names: &[&str], reason: &str) -> Result<(), ShellError> {
+        self.add_commands(
+            names
+                .iter()
+                .map(|name| Box::new(Denied::new(*name, reason)) as Box<dyn Command>),
+        )
+    }
+
+    /// Shadow `date now` so it always returns `now`. Relative dates like "now" are refused by
+    /// `into datetime`, which would read the real clock for them.
+    pub fn freeze_time(&mut self, now: DateTime<FixedOffset>) -> Result<(), ShellError> {
+        self.add_command(Box::new(FrozenDateNow::new(now)))?;
+        self.clock = Clock::Frozen(now);
+        self.refuse_relative_dates()
+    }
+
+    /// Drive `sleep` and `date now` from `clock`, so waiting takes no real time. Like
+    /// [`Engine::freeze_time`], this takes relative dates away from `into datetime`.
+    pub fn use_virtual_clock(&mut self, clock: VirtualClock) -> Result<(), ShellError> {
+        self.add_commands([
+            Box::new(VirtualSleep::new(clock.clone())) as Box<dyn Command>,
+            Box::new(VirtualDateNow::new(clock.clone())),
+        ])?;
+        self.clock = Clock::Virtual(clock);
+        self.refuse_relative_dates()
+    }
+
+    /// The time `date now` sees: the system clock, unless the engine's time is frozen, virtual or
+    /// denied altogether.
+    pub fn clock(&self) -> Clock {
+        self.clock.clone()
+    }
+
+    /// Swap `into datetime`, if there is one, for [`AbsoluteIntoDatetime`].
+    fn refuse_relative_dates(&mut self) -> Result<(), ShellError> {
+        if self.has_command("into datetime") {
+            self.add_command(Box::new(AbsoluteIntoDatetime))?;
+        }
+        Ok(())
+    }
+
+    /// Register `secret get`, backed by `store`, and `secret reveal`.
+    pub fn add_secrets(&mut self, store: SecretStore) -> Result<(), ShellError> {
+        self.add_commands([
+            Box::new(SecretGet::new(store)) as Box<dyn Command>,
+            Box::new(SecretReveal),
+        ])
+    }
+
+    /// Register `lookup`, serving rows from `tables` by name.
+    pub fn add_lookup_tables(
+        &mut self,
+        tables: HashMap<String, LookupTable>,
+    ) -> Result<(), ShellError> {
+        self.add_command(Box::new(Lookup::new(tables)))
+    }
+
+    /// Register `extract`, matching against `patterns` by name.
+    pub fn add_patterns(&mut self, patterns: HashMap<String, Regex>) -> Result<(), ShellError> {
+        self.add_command(Box::new(Extract::new(patterns)))
+    }
+
+    /// Define `$name` as a constant holding `value`, visible to everything evaluated afterwards.
+    pub fn add_constant(&mut self, name: &str, value: Value) -> Result<(), ShellError> {
+        let mut working_set = StateWorkingSet::new(&self.state);
+        let var_id = working_set.add_variable(
+            name.as_bytes().to_vec(),
+            Span::unknown(),
+            value.get_type(),
+            false,
+        );
+        working_set.set_variable_const_val(var_id, value.clone());
+        self.state.merge_delta(working_set.render())?;
+        self.constants.push((var_id, value));
+        self.parsed.clear();
+        Ok(())
+    }
+
+    /// A fresh stack with the constants on it.
+    fn stack(&self) -> Stack {
+        let mut stack = Stack::new();
+        for (var_id, value) in &self.constants {
+            stack.add_var(*var_id, value.clone());
+        }
+        stack
+    }
+
+    /// Abort evaluation once it has used more than `limit` of CPU time, with a "CPU time limit
+    /// exceeded" error. Each call to [`Engine::eval`] starts a fresh budget, which stays in force while its
+    /// output streams and covers the closures run on it. See [`CpuBudget`].
+    pub fn limit_cpu(&mut self, limit: Duration) {
+        self.cpu_limit = Some(limit);
+    }
+
+    /// Abort evaluation once the heap has grown by more than `bytes`, with a "Memory limit
+    /// exceeded" error. Like [`Engine::limit_cpu`], each evaluation starts from the current heap
+    /// size. This only works with [`memory::CountingAllocator`] installed as the global allocator.
+    ///
+    /// The heap is the whole process's, so there is one memory limit per process: the engine that
+    /// last started a run with [`Engine::eval`] or [`Engine::rearm_limits`] holds it, and
+    /// allocations by other engines, forks and threads count against it too. Closures run on an
+    /// engine that isn't armed yet don't take it over.
+    pub fn limit_memory(&mut self, bytes: usize) {
+        self.memory_limit = Some(bytes);
+    }
+
+    /// Interrupt evaluation once `timeout` of wall-clock time has passed, with an "Evaluation
+    /// timed out" error. Unlike [`Engine::limit_cpu`], this also stops commands that block, like
+    /// `sleep`, as long as they check for interrupts.
+    pub fn timeout(&mut self, timeout: Duration) {
+        self.timeout = Some(timeout);
+    }
+
+    /// Start fresh CPU, memory and time budgets, as [`Engine::eval`] does. Closures run under the
+    /// budgets already in force, so they share them with the stream they're applied to; a host
+    /// running closures on their own, one job after another, calls this between jobs. An engine
+    /// that hasn't been armed yet is armed by the first closure it runs.
+    pub fn rearm_limits(&self) -> Result<(), ShellError> {
+        self.arm(true)
+    }
+
+    /// Arm the limits, taking over the process's memory limit if `watch_memory`.
+    fn arm(&self, watch_memory: bool) -> Result<(), ShellError> {
+        self.armed.store(true, Ordering::Relaxed);
+        self.interrupt.store(false, Ordering::Relaxed);
+        self.cpu_exhausted.store(false, Ordering::Relaxed);
+        if let Some(limit) = self.cpu_limit {
+            let budget = CpuBudget::new(limit, self.interrupt.clone(), self.cpu_exhausted.clone());
+            self.state
+                .activate_debugger(Box::new(budget))
+                .map_err(|_| ShellError::GenericError {
+                    error: "Could not start the CPU budget".into(),
+                    msg: "poisoned debugger lock".into(),
+                    span: None,
+                    help: None,
+                    inner: vec![],
+                })?;
+        }
+        if let Some(limit) = self.memory_limit.filter(|_| watch_memory) {
+            memory::watch(limit, self.interrupt.clone());
+        }
+        if let Some(timeout) = self.timeout {
+            self.watchdog.arm(timeout, self.interrupt.clone());
+        }
+        Ok(())
+    }
+
+    /// The limit evaluation ran into since the limits were last armed, if any.
+    pub fn exceeded_limit(&self) -> Option<Limit> {
+        if self.cpu_limit.is_some() && self.cpu_exhausted.load(Ordering::Relaxed) {
+            Some(Limit::Cpu)
+        } else if self.timeout.is_some() && self.watchdog.fired() {
+            Some(Limit::Timeout)
+        } else if self.memory_limit.is_some() && memory::exceeded() {
+            Some(Limit::Memory)
+        } else {
+            None
+        }
+    }
+
+    /// The error for the limit evaluation ran into since the limits were last armed, if any. A
+    /// stream cut short by one just ends, so whoever drains it checks this afterwards.
+    pub fn limit_exceeded(&self) -> Option<ShellError> {
+        let (error, msg) = match self.exceeded_limit()? {
+            Limit::Cpu => (
+                CPU_LIMIT_ERROR,
+                format!(
+                    "evaluation used more than {:?} of CPU time",
+                    self.cpu_limit?
+                ),
+            ),
+            Limit::Timeout => (
+                TIMEOUT_ERROR,
+                format!("evaluation ran for more than {:?}", self.timeout?),
+            ),
+            Limit::Memory => (
+                MEMORY_LIMIT_ERROR,
+                format!(
+                    "evaluation allocated more than {} bytes",
+                    self.memory_limit?
+                ),
+            ),
+        };
+        Some(ShellError::GenericError {
+            error: error.into(),
+            msg,
+            span: None,
+            help: None,
+            inner: vec![],
+        })
+    }
+
+    /// Run `eval` under the CPU, memory and time budgets, if there are limits; `fresh` ones, or
+    /// the ones in force.
+    fn with_budget(
+        &self,
+        fresh: bool,
+        eval: impl FnOnce() -> Result<PipelineData, ShellError>,
+    ) -> Result<PipelineData, ShellError> {
+        if self.cpu_limit.is_none() && self.memory_limit.is_none() && self.timeout.is_none() {
+            return eval();
+        }
+        if fresh {
+            self.arm(true)?;
+        } else if !self.armed.load(Ordering::Relaxed) {
+            self.arm(false)?;
+        }
+
+        let result = eval();
+        match self.limit_exceeded() {
+            Some(err) => Err(err),
+            None => result,
+        }
+    }
+
+    /// How long building this engine took, phase by phase.
+    pub fn startup_report(&self) -> &StartupReport {
+        &self.startup
+    }
+
+    /// Report which side-effectful commands this engine exposes.
+    pub fn capabilities(&self) -> Capabilities {
+        Capabilities::of(self)
+    }
+
+    /// Parse `source` and merge the resulting delta into the engine. Parsing the same source
+    /// again returns the cached block without touching the parser.
+    pub fn parse(&mut self, source: &str) -> Result<Arc<Block>, ShellError> {
+        self.parse_named(None, source)
+    }
+
+    /// Like [`Engine::parse`], with `source` registered under `fname` so error spans point into
+    /// that file.
+    pub fn parse_file(&mut self, fname: &str, source: &str) -> Result<Arc<Block>, ShellError> {
+        self.parse_named(Some(fname), source)
+    }
+
+    fn parse_named(&mut self, fname: Option<&str>, source: &str) -> Result<Arc<Block>, ShellError> {
+        let key = (fname.map(str::to_string), source.to_string());
+        if let Some(block) = self.parsed.get(&key) {
+            return Ok(block.clone());
+        }
+
+        let mut working_set = StateWorkingSet::new(&self.state);
+        let block = nu_parser::parse(&mut working_set, fname, source.as_bytes(), false);
+
+        let checked = match working_set.parse_errors.first() {
+            Some(err) => Err(ShellError::GenericError {
+                error: PARSE_ERROR.into(),
+                msg: err.to_string(),
+                span: Some(err.span()),
+                help: None,
+                inner: vec![],
+            }),
+            None => self.check_policy(&working_set, &block),
+        };
+        if let Err(err) = checked {
+            // The rest of the delta is dropped, but the file is kept so the error's span into it
+            // can still be rendered. It lands where the parser put it, as nothing merged since.
+            if let Some(fname) = fname {
+                self.state.add_file(fname.into(), source.as_bytes().into());
+            }
+            return Err(err);
+        }
+        self.state.merge_delta(working_set.render())?;
+        self.parsed.insert(key, block.clone());
+        Ok(block)
+    }
+
+    /// Reject parsed code that calls a denied command, or gets around one through syntax rather
+    /// than calls, before any of it runs.
+    fn check_policy(&self, working_set: &StateWorkingSet, block: &Block) -> Result<(), ShellError> {
+        let policy_error = |msg: &str, span: Span| ShellError::GenericError {
+            error: POLICY_ERROR.into(),
+            msg: msg.into(),
+            span: Some(span),
+            help: None,
+            inner: vec![],
+        };
+
+        // The top-level block isn't part of the delta; the ones nested in it are.
+        let elements = std::iter::once(block)
+            .chain(working_set.delta.blocks.iter().map(|block| &**block))
+            .flat_map(|block| &block.pipelines)
+            .flat_map(|pipeline| &pipeline.elements);
+        for element in elements {
+            if self.deny_file_redirection {
+                let targets = match &element.redirection {
+                    Some(PipelineRedirection::Single { target, .. }) => vec![target],
+                    Some(PipelineRedirection::Separate { out, err }) => vec![out, err],
+                    None => vec![],
+                };
+                if let Some(file) = targets
+                    .into_iter()
+                    .find(|target| matches!(target, RedirectionTarget::File { .. }))
+                {
+                    return Err(policy_error("Redirecting into a file", file.span()));
+                }
+            }
+            if self.deny_file_loading {
+                if let Expr::Call(call) = &element.expr.expr {
+                    let name = working_set.get_decl(call.decl_id).name();
+                    if FILE_LOADING_KEYWORDS.contains(&name) {
+                        return Err(policy_error("Loading another file", call.head));
+                    }
+                }
+            }
+        }
+        if self.deny_nu_variable {
+            if let Some((span, _)) = flatten_block(working_set, block)
+                .into_iter()
+                .find(|(_, shape)| *shape == FlatShape::Variable(NU_VARIABLE_ID))
+            {
+                return Err(policy_error("Reading `$nu`", span));
+            }
+        }
+        match denied_calls(working_set, block).into_iter().next() {
+            Some((name, reason, span)) => Err(denied_error(&name, &reason, span)),
+            None => Ok(()),
+        }
+    }
+
+    /// Parse and evaluate `source` with `input` on a fresh stack. The stdout of a trailing
+    /// external command is captured into the result rather than inherited.
+    pub fn eval(&mut self, source: &str, input: PipelineData) -> Result<PipelineData, ShellError> {
+        let block = self.parse(source)?;
+        self.eval_block(&block, input)
+    }
+
+    /// Like [`Engine::eval`], for `source` read from the file `fname`. See [`Engine::parse_file`].
+    pub fn eval_file(
+        &mut self,
+        fname: &str,
+        source: &str,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let block = self.parse_file(fname, source)?;
+        self.eval_block(&block, input)
+    }
+
+    fn eval_block(&self, block: &Block, input: PipelineData) -> Result<PipelineData, ShellError> {
+        let mut stack = self.stack().capture();
+        self.with_budget(true, || {
+            get_eval_block_with_early_return(&self.state)(&self.state, &mut stack, block, input)
+        })
+    }
+
+    /// Like [`Engine::eval`], but collects the output into a value before returning, so the
+    /// engine's limits cover producing all of it rather than just the start of a stream.
+    pub fn eval_value(&mut self, source: &str, input: PipelineData) -> Result<Value, ShellError> {
+        let block = self.parse(source)?;
+        let mut stack = self.stack().capture();
+        self.with_budget(true, || {
+            let output = get_eval_block_with_early_return(&self.state)(
+                &self.state,
+                &mut stack,
+                &block,
+                input,
+            )?;
+            Ok(PipelineData::Value(
+                output.into_value(Span::unknown())?,
+                None,
+            ))
+        })?
+        .into_value(Span::unknown())
+    }
+
+    /// Parse and evaluate `source`, which is expected to produce a closure.
+    pub fn parse_closure(&mut self, source: &str) -> Result<Closure, ShellError> {
+        let value = self
+            .eval(source, PipelineData::empty())?
+            .into_value(Span::unknown())?;
+        value.into_closure()
+    }
+
+    /// Run `closure` with `input` as its pipeline input.
+    pub fn eval_closure(
+        &self,
+        closure: &Closure,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        self.with_budget(false, || {
+            ClosureEvalOnce::new(&self.state, &self.stack(), closure.clone()).run_with_input(input)
+        })
+    }
+
+    /// Run `closure` with `value` as both its pipeline input and its first argument.
+    pub fn eval_closure_with_value(
+        &self,
+        closure: &Closure,
+        value: Value,
+    ) -> Result<PipelineData, ShellError> {
+        self.eval_closure_with_args(closure, [value.clone()], PipelineData::Value(value, None))
+    }
+
+    /// Run `closure` with `input`, binding `args` to its positionals in order. The closure takes
+    /// as many as it declares, so `{|| }`, `{|x| }` and `{|x, n| }` can all be given the same
+    /// arguments; optional positionals left over get their default, or null.
+    pub fn eval_closure_with_args(
+        &self,
+        closure: &Closure,
+        args: impl IntoIterator<Item = Value>,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let signature = &self.state.get_block(closure.block_id).signature;
+        let positionals = signature.required_positional.len() + signature.optional_positional.len();
+        let mut args: Vec<Value> = args.into_iter().take(positionals).collect();
+        for optional in signature.optional_positional.iter().skip(
+            args.len()
+                .saturating_sub(signature.required_positional.len()),
+        ) {
+            args.push(
+                optional
+                    .default_value
+                    .clone()
+                    .unwrap_or_else(|| Value::nothing(Span::unknown())),
+            );
+        }
+
+        self.with_budget(false, || {
+            args.into_iter()
+                .fold(
+                    ClosureEvalOnce::new(&self.state, &self.stack(), closure.clone()),
+                    ClosureEvalOnce::add_arg,
+                )
+                .run_with_input(input)
+        })
+    }
+
+    /// Run `closure` with `df` as its input, returning the DataFrame it produces.
+    ///
+    /// The DataFrame is handed over as a custom value, so it never round-trips through rows.
+    #[cfg(feature = "polars")]
+    pub fn eval_with_dataframe(
+        &self,
+        closure: &Closure,
+        df: polars::frame::DataFrame,
+    ) -> Result<polars::frame::DataFrame, ShellError> {
+        use crate::dataframe::DataFrameValue;
+
+        let input = DataFrameValue::new(df).into_value(Span::unknown());
+        let output = self
+            .eval_closure(closure, PipelineData::Value(input, None))?
+            .into_value(Span::unknown())?;
+        DataFrameValue::try_from_value(output)
+    }
+}
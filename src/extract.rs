@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use nu_engine::command_prelude::*;
+use regex::Regex;
+
+/// `extract <name>`: the named capture groups of a host-compiled pattern as a record, or null if
+/// the input doesn't match. Groups that didn't take part in the match are null.
+#[derive(Clone)]
+pub struct Extract {
+    patterns: Arc<HashMap<String, Regex>>,
+}
+
+impl Extract {
+    pub fn new(patterns: HashMap<String, Regex>) -> Self {
+        Self {
+            patterns: Arc::new(patterns),
+        }
+    }
+}
+
+impl Command for Extract {
+    fn name(&self) -> &str {
+        "extract"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("extract")
+            .input_output_types(vec![(Type::String, Type::Any)])
+            .required("name", SyntaxShape::String, "Name of the pattern.")
+            .category(Category::Strings)
+    }
+
+    fn usage(&self) -> &str {
+        "Match the input against a pattern compiled by the host, returning its named groups."
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let name: Spanned<String> = call.req(engine_state, stack, 0)?;
+        let pattern = self
+            .patterns
+            .get(&name.item)
+            .ok_or_else(|| ShellError::GenericError {
+                error: format!("Pattern '{}' is not defined", name.item),
+                msg: "no pattern with this name".into(),
+                span: Some(name.span),
+                help: None,
+                inner: vec![],
+            })?;
+        let text = input.into_value(call.head)?.coerce_into_string()?;
+        let head = call.head;
+        let value = match pattern.captures(&text) {
+            Some(captures) => Value::record(
+                pattern
+                    .capture_names()
+                    .flatten()
+                    .map(|group| {
+                        let val = captures
+                            .name(group)
+                            .map_or(Value::nothing(head), |m| Value::string(m.as_str(), head));
+                        (group.to_string(), val)
+                    })
+                    .collect(),
+                head,
+            ),
+            None => Value::nothing(head),
+        };
+        Ok(value.into_pipeline_data())
+    }
+}
@@ -0,0 +1,74 @@
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::Arc;
+
+use maxminddb::{MaxMindDBError, Reader};
+use nu_engine::command_prelude::*;
+
+use crate::json::json_to_value;
+
+/// `geoip lookup <ip>`: everything a MaxMind database knows about an address, or null if it
+/// knows nothing.
+#[derive(Clone)]
+pub struct GeoipLookup {
+    reader: Arc<Reader<Vec<u8>>>,
+}
+
+impl GeoipLookup {
+    /// Load the database at `path`, e.g. a GeoLite2 City `.mmdb` file.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let reader = Reader::open_readfile(path)
+            .map_err(|err| format!("Can't open GeoIP database {}: {err}", path.display()))?;
+        Ok(Self {
+            reader: Arc::new(reader),
+        })
+    }
+}
+
+impl Command for GeoipLookup {
+    fn name(&self) -> &str {
+        "geoip lookup"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("geoip lookup")
+            .input_output_types(vec![(Type::Nothing, Type::Any)])
+            .required("ip", SyntaxShape::String, "IPv4 or IPv6 address.")
+            .category(Category::Network)
+    }
+
+    fn usage(&self) -> &str {
+        "Look an IP address up in the host's GeoIP database."
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let ip: Spanned<String> = call.req(engine_state, stack, 0)?;
+        let addr: IpAddr = ip.item.parse().map_err(|err| ShellError::GenericError {
+            error: format!("Invalid IP address '{}'", ip.item),
+            msg: format!("{err}"),
+            span: Some(ip.span),
+            help: None,
+            inner: vec![],
+        })?;
+        let value = match self.reader.lookup::<serde_json::Value>(addr) {
+            Ok(found) => json_to_value(found, call.head),
+            Err(MaxMindDBError::AddressNotFoundError(_)) => Value::nothing(call.head),
+            Err(err) => {
+                return Err(ShellError::GenericError {
+                    error: "GeoIP lookup failed".into(),
+                    msg: err.to_string(),
+                    span: Some(ip.span),
+                    help: None,
+                    inner: vec![],
+                })
+            }
+        };
+        Ok(value.into_pipeline_data())
+    }
+}
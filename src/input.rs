@@ -0,0 +1,202 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+use nu_protocol::{record, ListStream, ShellError, Signals, Span, Value};
+
+use crate::clock::Clock;
+use crate::datasets::csv_rows;
+use crate::json::json_to_value;
+
+/// How structured input is encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    /// A single JSON document.
+    Json,
+    /// One JSON document per line, read as a list.
+    Ndjson,
+    /// A header line followed by rows, read as a table of strings.
+    Csv,
+    /// CSV with tab-separated fields.
+    Tsv,
+    /// Plain text, read as a list of `{line, n, received_at}` records, `n` counting from 1.
+    /// `received_at` comes from the engine's [`Clock`], and is null if it has none.
+    Lines,
+    /// Anything, read as a list of binary chunks of up to `chunk_size` bytes.
+    Bytes { chunk_size: usize },
+}
+
+/// Size of the chunks `bytes` input is read in, unless given.
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+impl std::str::FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "ndjson" => Ok(Self::Ndjson),
+            "csv" => Ok(Self::Csv),
+            "tsv" => Ok(Self::Tsv),
+            "lines" => Ok(Self::Lines),
+            "bytes" => Ok(Self::Bytes {
+                chunk_size: DEFAULT_CHUNK_SIZE,
+            }),
+            _ => Err(format!(
+                "Unknown input format '{s}', expected json, ndjson, csv, tsv, lines or bytes"
+            )),
+        }
+    }
+}
+
+/// Where lines that don't parse are written, as `{n, line, error}` records.
+pub type Malformed = Box<dyn Write + Send>;
+
+type Items = Box<dyn Iterator<Item = Result<Value, String>> + Send>;
+
+/// Read all of `reader` as `format` into a single value. Line-based formats end each line with
+/// `delimiter`, normally `\n`. NDJSON lines that don't parse fail the read, unless there's a
+/// `malformed` stream to write them to.
+pub fn read(
+    reader: impl Read + Send + 'static,
+    format: InputFormat,
+    delimiter: u8,
+    malformed: Option<Malformed>,
+    clock: Clock,
+) -> Result<Value, String> {
+    if format == InputFormat::Json {
+        let json =
+            serde_json::from_reader(reader).map_err(|err| format!("Invalid JSON input: {err}"))?;
+        return Ok(json_to_value(json, Span::unknown()));
+    }
+    let vals = items(reader, format, delimiter, malformed, clock)?.collect::<Result<_, _>>()?;
+    Ok(Value::list(vals, Span::unknown()))
+}
+
+/// Like [`read`], but the items are read as the stream is consumed rather than up front, so
+/// unbounded input doesn't have to fit in memory. Input that can't be read ends up in the stream
+/// as an error value. A single JSON document can't be streamed.
+pub fn stream(
+    reader: impl Read + Send + 'static,
+    format: InputFormat,
+    delimiter: u8,
+    malformed: Option<Malformed>,
+    clock: Clock,
+    signals: Signals,
+) -> Result<ListStream, String> {
+    if format == InputFormat::Json {
+        return Err("JSON input can't be streamed, use ndjson".into());
+    }
+    let span = Span::unknown();
+    let items = items(reader, format, delimiter, malformed, clock)?.map(move |item| {
+        item.unwrap_or_else(|msg| {
+            Value::error(
+                ShellError::GenericError {
+                    error: "Invalid input".into(),
+                    msg,
+                    span: None,
+                    help: None,
+                    inner: vec![],
+                },
+                span,
+            )
+        })
+    });
+    Ok(ListStream::new(items, span, signals))
+}
+
+/// The items of a list-shaped `format`, parsed one at a time.
+fn items(
+    reader: impl Read + Send + 'static,
+    format: InputFormat,
+    delimiter: u8,
+    mut malformed: Option<Malformed>,
+    clock: Clock,
+) -> Result<Items, String> {
+    let span = Span::unknown();
+    Ok(match format {
+        InputFormat::Json => unreachable!("a JSON document is a single value"),
+        InputFormat::Ndjson => Box::new(lines(reader, delimiter).enumerate().filter_map(
+            move |(n, line)| {
+                let line = match line {
+                    Ok(line) if line.trim().is_empty() => return None,
+                    Ok(line) => line,
+                    Err(err) => return Some(Err(err)),
+                };
+                match (serde_json::from_str(&line), &mut malformed) {
+                    (Ok(json), _) => Some(Ok(json_to_value(json, span))),
+                    (Err(err), Some(malformed)) => {
+                        let report = serde_json::json!({
+                            "n": n + 1,
+                            "line": line,
+                            "error": err.to_string(),
+                        });
+                        writeln!(malformed, "{report}")
+                            .map_err(|err| format!("Can't write malformed input: {err}"))
+                            .err()
+                            .map(Err)
+                    }
+                    (Err(err), None) => {
+                        Some(Err(format!("Invalid JSON on input line {}: {err}", n + 1)))
+                    }
+                }
+            },
+        )),
+        InputFormat::Csv | InputFormat::Tsv => {
+            let delimiter = if format == InputFormat::Tsv {
+                b'\t'
+            } else {
+                b','
+            };
+            Box::new(
+                csv_rows(reader, delimiter, "input")?
+                    .map(move |row| row.map(|row| Value::record(row, span))),
+            )
+        }
+        InputFormat::Lines => {
+            Box::new(lines(reader, delimiter).enumerate().map(move |(n, line)| {
+                Ok(Value::record(
+                    record! {
+                        "line" => Value::string(line?, span),
+                        "n" => Value::int(n as i64 + 1, span),
+                        "received_at" => clock
+                            .now()
+                            .map_or_else(|| Value::nothing(span), |now| Value::date(now, span)),
+                    },
+                    span,
+                ))
+            }))
+        }
+        InputFormat::Bytes { chunk_size } => {
+            Box::new(chunks(reader, chunk_size).map(move |chunk| {
+                chunk
+                    .map(|chunk| Value::binary(chunk, span))
+                    .map_err(|err| format!("Can't read input: {err}"))
+            }))
+        }
+    })
+}
+
+/// `reader` in chunks of `chunk_size` bytes, the last one possibly shorter.
+fn chunks(mut reader: impl Read, chunk_size: usize) -> impl Iterator<Item = io::Result<Vec<u8>>> {
+    std::iter::from_fn(move || {
+        let mut chunk = Vec::with_capacity(chunk_size);
+        match (&mut reader)
+            .take(chunk_size as u64)
+            .read_to_end(&mut chunk)
+        {
+            Ok(0) => None,
+            Ok(_) => Some(Ok(chunk)),
+            Err(err) => Some(Err(err)),
+        }
+    })
+}
+
+/// The lines of `reader`, without their `delimiter`, or the `\r\n` ending a line of text.
+fn lines(reader: impl Read, delimiter: u8) -> impl Iterator<Item = Result<String, String>> {
+    BufReader::new(reader).split(delimiter).map(move |line| {
+        let mut line = line.map_err(|err| format!("Can't read input: {err}"))?;
+        if delimiter == b'\n' && line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        String::from_utf8(line).map_err(|err| format!("Input isn't UTF-8: {err}"))
+    })
+}
@@ -0,0 +1,146 @@
+use std::path::{Component, Path, PathBuf};
+
+use nu_engine::command_prelude::*;
+
+/// Characters that make an unquoted path a glob pattern.
+const GLOB_CHARS: &[char] = &['*', '?', '['];
+
+/// Wraps a filesystem command so every path it's given has to stay under `root`.
+///
+/// Paths are checked before the command runs: they may not use `..` or `~`, absolute ones have to
+/// be under `root`, and relative ones are resolved against the current directory, which has to
+/// be under `root` too. The longest existing part of each path is canonicalized, so a symlink
+/// named in the path can't lead out either. Glob patterns are refused: their matches aren't known
+/// until the command expands them, and one of them could be such a symlink.
+#[derive(Clone)]
+pub struct Jailed {
+    inner: Box<dyn Command>,
+    root: PathBuf,
+    /// Named flags that take a path, like `save --stderr`.
+    path_flags: &'static [&'static str],
+    /// Whether the command reads its path from the input when it isn't given one, like `open`.
+    path_input: bool,
+}
+
+impl Jailed {
+    /// `root` has to be canonical already.
+    pub fn new(
+        inner: Box<dyn Command>,
+        root: PathBuf,
+        path_flags: &'static [&'static str],
+    ) -> Self {
+        Self {
+            inner,
+            root,
+            path_flags,
+            path_input: false,
+        }
+    }
+
+    /// Check the input as a path too when the command is called without one, as `open` reads it
+    /// from there.
+    pub fn with_path_input(mut self) -> Self {
+        self.path_input = true;
+        self
+    }
+
+    fn limited(&self, msg: &str, span: Span) -> ShellError {
+        ShellError::GenericError {
+            error: format!(
+                "`{}` is limited to {}",
+                self.inner.name(),
+                self.root.display()
+            ),
+            msg: msg.into(),
+            span: Some(span),
+            help: None,
+            inner: vec![],
+        }
+    }
+
+    fn check(&self, cwd: &Path, path: &Spanned<String>) -> Result<(), ShellError> {
+        let escapes = |msg: &str| self.limited(msg, path.span);
+        if path.item.contains("..") {
+            return Err(escapes("paths may not use `..`"));
+        }
+        if path.item.starts_with('~') {
+            return Err(escapes("paths may not use `~`"));
+        }
+
+        let full = cwd.join(&path.item);
+        let existing = full
+            .ancestors()
+            .find_map(|ancestor| ancestor.canonicalize().ok())
+            .unwrap_or_default();
+        if full.components().any(|c| c == Component::ParentDir) || !existing.starts_with(&self.root)
+        {
+            return Err(escapes("this path is outside of it"));
+        }
+        Ok(())
+    }
+}
+
+impl Command for Jailed {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn signature(&self) -> Signature {
+        self.inner.signature()
+    }
+
+    fn usage(&self) -> &str {
+        self.inner.usage()
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let cwd = engine_state.cwd(Some(stack))?.into_std_path_buf();
+        let glob_refused = |span| {
+            self.limited(
+                "glob patterns can't be checked against it; quote the path",
+                span,
+            )
+        };
+        let mut paths = vec![];
+        for arg in call.rest::<Value>(engine_state, stack, 0)? {
+            let span = arg.span();
+            let path = match arg {
+                Value::Glob {
+                    val,
+                    no_expand: false,
+                    ..
+                } if val.contains(GLOB_CHARS) => return Err(glob_refused(span)),
+                Value::Glob { val, .. } => val,
+                arg => arg.coerce_into_string()?,
+            };
+            paths.push(path.into_spanned(span));
+        }
+        // A path read from the input is always expanded as a glob.
+        if self.path_input && !call.has_positional_args(stack, 0) {
+            if let PipelineData::Value(value, _) = &input {
+                let path = value.coerce_string()?;
+                if path.contains(GLOB_CHARS) {
+                    return Err(glob_refused(value.span()));
+                }
+                paths.push(path.into_spanned(value.span()));
+            }
+        }
+        for flag in self.path_flags {
+            paths.extend(call.get_flag::<Spanned<String>>(engine_state, stack, flag)?);
+        }
+        // With no path, the command works on the current directory.
+        if paths.is_empty() {
+            paths.push(".".to_string().into_spanned(call.head));
+        }
+        for path in &paths {
+            self.check(&cwd, path)?;
+        }
+        self.inner.run(engine_state, stack, call, input)
+    }
+}
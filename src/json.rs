@@ -0,0 +1,65 @@
+use nu_protocol::{Record, Span, Value};
+use serde_json::{json, Map, Number};
+
+/// Convert a nu [`Value`] into JSON.
+///
+/// Structured values map onto their JSON counterparts. Values JSON has no type for are reduced:
+/// filesizes to bytes, durations to nanoseconds, dates to RFC 3339 strings, binary to an array of
+/// bytes and ranges to their `start..end` form. Custom values are converted through their base
+/// value.
+pub fn value_to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Nothing { .. } => serde_json::Value::Null,
+        Value::Bool { val, .. } => json!(val),
+        Value::Int { val, .. } => json!(val),
+        Value::Float { val, .. } => Number::from_f64(*val)
+            .map(serde_json::Value::Number)
+            .unwrap_or(serde_json::Value::Null),
+        Value::Filesize { val, .. } => json!(val),
+        Value::Duration { val, .. } => json!(val),
+        Value::Date { val, .. } => json!(val.to_rfc3339()),
+        Value::String { val, .. } => json!(val),
+        Value::Glob { val, .. } => json!(val),
+        Value::Binary { val, .. } => json!(val),
+        Value::Range { val, .. } => json!(val.to_string()),
+        Value::CellPath { val, .. } => json!(val.to_string()),
+        Value::Closure { val, .. } => json!(format!("<Closure {}>", val.block_id)),
+        Value::Error { error, .. } => json!({ "error": error.to_string() }),
+        Value::List { vals, .. } => vals.iter().map(value_to_json).collect(),
+        Value::Record { val, .. } => val
+            .iter()
+            .map(|(col, val)| (col.clone(), value_to_json(val)))
+            .collect::<Map<_, _>>()
+            .into(),
+        Value::Custom { val, internal_span } => match val.to_base_value(*internal_span) {
+            Ok(base) => value_to_json(&base),
+            Err(_) => json!(format!("<{}>", val.type_name())),
+        },
+    }
+}
+
+/// Convert JSON into a nu [`Value`], with every value given `span`. Numbers that fit an `i64`
+/// become ints and the rest floats.
+pub fn json_to_value(json: serde_json::Value, span: Span) -> Value {
+    match json {
+        serde_json::Value::Null => Value::nothing(span),
+        serde_json::Value::Bool(val) => Value::bool(val, span),
+        serde_json::Value::Number(num) => match num.as_i64() {
+            Some(val) => Value::int(val, span),
+            None => Value::float(num.as_f64().unwrap_or(f64::NAN), span),
+        },
+        serde_json::Value::String(val) => Value::string(val, span),
+        serde_json::Value::Array(vals) => Value::list(
+            vals.into_iter()
+                .map(|val| json_to_value(val, span))
+                .collect(),
+            span,
+        ),
+        serde_json::Value::Object(map) => Value::record(
+            map.into_iter()
+                .map(|(col, val)| (col, json_to_value(val, span)))
+                .collect::<Record>(),
+            span,
+        ),
+    }
+}
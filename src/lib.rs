@@ -0,0 +1,52 @@
+//! The minimum amount of nu to run some nu, as a library.
+//!
+//! ```no_run
+//! use mini_nu::Engine;
+//! use nu_protocol::{PipelineData, Span, Value};
+//!
+//! let mut engine = Engine::new()?;
+//!
+//! let value = engine
+//!     .eval(r#""foo" | str upcase"#, PipelineData::empty())?
+//!     .into_value(Span::unknown())?;
+//!
+//! let closure = engine.parse_closure("{|x| $x * 2 }")?;
+//! let doubled = engine
+//!     .eval_closure_with_value(&closure, Value::test_int(21))?
+//!     .into_value(Span::unknown())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+pub mod budget;
+pub mod capabilities;
+pub mod clock;
+pub mod config;
+#[cfg(feature = "polars")]
+pub mod dataframe;
+pub mod datasets;
+pub mod dedup;
+pub mod engine;
+pub mod extract;
+#[cfg(feature = "geoip")]
+pub mod geoip;
+pub mod input;
+pub mod jail;
+pub mod json;
+pub mod lint;
+pub mod lookup;
+pub mod memory;
+pub mod notify;
+pub mod pool;
+pub mod profiles;
+pub mod registry;
+pub mod sandbox;
+pub mod secrets;
+pub mod startup;
+pub mod tee;
+#[cfg(feature = "useragent")]
+pub mod useragent;
+pub mod vfs;
+
+pub use engine::{Engine, EngineBuilder, Limit};
+pub use pool::EnginePool;
+pub use sandbox::{eval_sandboxed, SandboxError, SandboxPolicy};
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+
+use nu_parser::{flatten_block, FlatShape};
+use nu_protocol::engine::StateWorkingSet;
+use nu_protocol::Category;
+use serde::Serialize;
+
+use crate::Engine;
+
+/// How a lint rule is reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Off,
+    Warn,
+    Error,
+}
+
+impl std::str::FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Self::Off),
+            "warn" => Ok(Self::Warn),
+            "error" => Ok(Self::Error),
+            _ => Err(format!(
+                "Invalid severity '{s}', expected off, warn or error"
+            )),
+        }
+    }
+}
+
+/// A pattern that misbehaves when nu is embedded rather than run as a shell.
+pub struct Rule {
+    pub name: &'static str,
+    pub message: &'static str,
+    /// Commands whose use triggers the rule.
+    decls: &'static [&'static str],
+}
+
+pub const RULES: &[Rule] = &[
+    Rule {
+        name: "cd",
+        message: "`cd` changes $env.PWD for the rest of the evaluation; use absolute paths instead",
+        decls: &["cd"],
+    },
+    Rule {
+        name: "env-pwd",
+        message: "assigning $env.PWD changes the working directory for the rest of the evaluation",
+        decls: &[],
+    },
+    Rule {
+        name: "input",
+        message: "`input` waits for a terminal that an embedded engine doesn't have",
+        decls: &["input", "input list", "input listen"],
+    },
+    Rule {
+        name: "loop",
+        message: "`loop` runs until it hits `break`; make sure every path can get there",
+        decls: &["loop"],
+    },
+];
+
+fn rule(name: &str) -> &'static Rule {
+    RULES
+        .iter()
+        .find(|rule| rule.name == name)
+        .expect("rule is defined")
+}
+
+/// Per-rule severities; rules not mentioned are reported as warnings.
+#[derive(Debug, Clone, Default)]
+pub struct RuleConfig {
+    severities: HashMap<String, Severity>,
+}
+
+impl RuleConfig {
+    pub fn set(&mut self, rule: &str, severity: Severity) -> Result<(), String> {
+        if !RULES.iter().any(|r| r.name == rule) {
+            return Err(format!("Unknown lint rule '{rule}'"));
+        }
+        self.severities.insert(rule.into(), severity);
+        Ok(())
+    }
+
+    pub fn severity(&self, rule: &str) -> Severity {
+        self.severities.get(rule).copied().unwrap_or(Severity::Warn)
+    }
+}
+
+/// The rule parse errors are reported under, always as errors.
+pub const PARSE_RULE: &str = "parse";
+
+/// The rule calls to commands Nushell has removed are reported under, always as errors: they
+/// only fail once they run.
+pub const REMOVED_RULE: &str = "removed";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    /// 1-based line and column of the offending code in the source.
+    pub line: usize,
+    pub column: usize,
+    /// Byte range of the offending code in the source.
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Parse `source` without evaluating it and report every parse error, then every call to a
+/// removed command or use of a discouraged pattern.
+pub fn lint(engine: &Engine, source: &str, config: &RuleConfig) -> Vec<Finding> {
+    let mut working_set = StateWorkingSet::new(&engine.state);
+    let offset = working_set.next_span_start();
+    let block = nu_parser::parse(&mut working_set, Some("lint"), source.as_bytes(), false);
+    let shapes = flatten_block(&working_set, &block);
+
+    // Spans outside the source, like a parse error's in another file, point at its start.
+    let finding = |rule, severity, message, start: usize, end: usize| {
+        let start = start.saturating_sub(offset).min(source.len());
+        let end = end.saturating_sub(offset).clamp(start, source.len());
+        Finding {
+            rule,
+            severity,
+            message,
+            line: source[..start].matches('\n').count() + 1,
+            column: start - source[..start].rfind('\n').map_or(0, |i| i + 1) + 1,
+            start,
+            end,
+        }
+    };
+    let mut findings: Vec<Finding> = working_set
+        .parse_errors
+        .iter()
+        .map(|err| {
+            let span = err.span();
+            finding(
+                PARSE_RULE,
+                Severity::Error,
+                err.to_string(),
+                span.start,
+                span.end,
+            )
+        })
+        .collect();
+    // The parser can report the same error more than once.
+    findings.dedup_by(|a, b| (a.start, a.end, &a.message) == (b.start, b.end, &b.message));
+    let report = |rule: &'static Rule, start: usize, end: usize| {
+        let severity = config.severity(rule.name);
+        (severity != Severity::Off)
+            .then(|| finding(rule.name, severity, rule.message.into(), start, end))
+    };
+
+    for (i, (span, shape)) in shapes.iter().enumerate() {
+        match shape {
+            FlatShape::InternalCall(decl_id) => {
+                let decl = working_set.get_decl(*decl_id);
+                if decl.signature().category == Category::Removed {
+                    findings.push(finding(
+                        REMOVED_RULE,
+                        Severity::Error,
+                        decl.usage().into(),
+                        span.start,
+                        span.end,
+                    ));
+                } else if let Some(rule) =
+                    RULES.iter().find(|rule| rule.decls.contains(&decl.name()))
+                {
+                    findings.extend(report(rule, span.start, span.end));
+                }
+            }
+            FlatShape::Operator if working_set.get_span_contents(*span) == b"=" => {
+                let target = shapes[..i]
+                    .last()
+                    .map(|(target, _)| working_set.get_span_contents(*target));
+                if target.is_some_and(|target| target == b"PWD" || target.ends_with(b".PWD")) {
+                    // `$env` and `PWD` may come through as separate shapes.
+                    let start = match i.checked_sub(2).map(|j| shapes[j].0) {
+                        Some(env) if working_set.get_span_contents(env) == b"$env" => env.start,
+                        _ => shapes[i - 1].0.start,
+                    };
+                    findings.extend(report(rule("env-pwd"), start, span.end));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    findings
+}
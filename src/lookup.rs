@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use nu_engine::command_prelude::*;
+
+use crate::datasets::read_csv;
+
+/// A CSV file loaded once and indexed by one of its columns, so repeated lookups don't re-read it.
+#[derive(Debug, Clone)]
+pub struct LookupTable {
+    rows: HashMap<String, Record>,
+}
+
+impl LookupTable {
+    /// Load `path`, indexing each row by its `key` column. Later rows win over earlier ones with
+    /// the same key. Fields are kept as strings.
+    pub fn load(path: &Path, key: &str) -> Result<Self, String> {
+        let rows = read_csv(path)?
+            .into_iter()
+            .map(|row| match row.get(key) {
+                Some(Value::String { val, .. }) => Ok((val.clone(), row)),
+                _ => Err(format!("{} has no '{key}' column", path.display())),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { rows })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Record> {
+        self.rows.get(key)
+    }
+}
+
+/// `lookup <name> <key>`: the row of a host-loaded [`LookupTable`], or null if there is none.
+#[derive(Clone)]
+pub struct Lookup {
+    tables: Arc<HashMap<String, LookupTable>>,
+}
+
+impl Lookup {
+    pub fn new(tables: HashMap<String, LookupTable>) -> Self {
+        Self {
+            tables: Arc::new(tables),
+        }
+    }
+}
+
+impl Command for Lookup {
+    fn name(&self) -> &str {
+        "lookup"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("lookup")
+            .input_output_types(vec![(Type::Nothing, Type::Any)])
+            .required("name", SyntaxShape::String, "Name of the lookup table.")
+            .required("key", SyntaxShape::Any, "Key of the row to fetch.")
+            .category(Category::Misc)
+    }
+
+    fn usage(&self) -> &str {
+        "Fetch a row from a lookup table loaded by the host, or null if the key isn't in it."
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let name: Spanned<String> = call.req(engine_state, stack, 0)?;
+        let key: Value = call.req(engine_state, stack, 1)?;
+        let table = self
+            .tables
+            .get(&name.item)
+            .ok_or_else(|| ShellError::GenericError {
+                error: format!("Lookup table '{}' is not loaded", name.item),
+                msg: "no table with this name".into(),
+                span: Some(name.span),
+                help: None,
+                inner: vec![],
+            })?;
+        let key = key.coerce_into_string()?;
+        Ok(match table.get(&key) {
+            Some(row) => Value::record(row.clone(), call.head),
+            None => Value::nothing(call.head),
+        }
+        .into_pipeline_data())
+    }
+}
@@ -1,52 +1,664 @@
-use nu_cli::gather_parent_env_vars;
-use nu_cmd_lang::create_default_context;
-use nu_command::add_shell_command_context;
-use nu_engine::eval_block_with_early_return;
-use nu_protocol::debugger::WithoutDebug;
-use nu_protocol::engine::{Stack, StateWorkingSet};
-use nu_protocol::{PipelineData, Span, Value};
+mod args;
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use args::{Args, Format, OnEpipe, RegistryCommand};
+use mini_nu::config::Config;
+use mini_nu::datasets;
+use mini_nu::dedup::DuplicateWindow;
+use mini_nu::input::{self, InputFormat, Malformed};
+use mini_nu::json::value_to_json;
+use mini_nu::lint::{self, Severity};
+use mini_nu::lookup::LookupTable;
+use mini_nu::memory::CountingAllocator;
+use mini_nu::registry::Registry;
+use mini_nu::secrets::SecretStore;
+use mini_nu::tee::{tee, Tee};
+use mini_nu::{Engine, SandboxPolicy};
+use nu_protocol::engine::{Closure, StateWorkingSet};
+use nu_protocol::{format_error, record, PipelineData, Record, ShellError, Span, Value};
+use regex::Regex;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// What a shell reports for a process killed by SIGPIPE (128 + 13).
+const EPIPE_EXIT_CODE: i32 = 141;
+
+/// Where the closure registry lives when there's no config dir to put it under.
+const REGISTRY_FALLBACK_DIR: &str = ".mini-nu/closures";
+
+/// Most results `--report` holds on to; any more are left out of the report.
+const REPORT_MAX_ROWS: usize = 1_000_000;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut engine_state = create_default_context();
-    engine_state = add_shell_command_context(engine_state);
+    let args = Args::parse()?;
+    let config = Config::load(&args.config)?;
+
+    // `date` and number formatting read these from the process environment, so they need to be
+    // in place before the engine gathers its `$env`.
+    if let Some(timezone) = &args.timezone {
+        std::env::set_var("TZ", timezone);
+    }
+    if let Some(locale) = &args.locale {
+        std::env::set_var("LC_ALL", locale);
+    }
+
+    let allow = match &args.allow_file {
+        Some(path) => {
+            let allowed = std::fs::read_to_string(path)
+                .map_err(|err| format!("Failed to read {}: {err}", path.display()))?;
+            Some(
+                allowed
+                    .lines()
+                    .map(|line| line.split('#').next().unwrap_or_default().trim())
+                    .filter(|name| !name.is_empty())
+                    .map(String::from)
+                    .collect(),
+            )
+        }
+        None => None,
+    };
+    let policy = SandboxPolicy {
+        // Profiles build up from the language core, so they don't get the host's `$env` either.
+        env: args.profiles.is_empty(),
+        profiles: args.profiles,
+        allow,
+        deny: args.deny,
+        read_only: args.read_only,
+        deny_network: args.deny_network,
+        deny_filesystem: args.deny_filesystem,
+        deterministic: args.sandbox,
+        frozen_now: args.freeze_time,
+        root: args.root,
+        max_cpu: args.max_cpu,
+        max_memory: args.max_memory,
+        timeout: args.timeout,
+    };
+    let mut engine = policy.builder().build()?;
+    if args.startup_report {
+        let report = engine.startup_report();
+        match args.format {
+            Format::Json => eprintln!("{}", serde_json::to_string_pretty(report)?),
+            Format::Ndjson => eprintln!("{}", serde_json::to_string(report)?),
+            Format::Text => {
+                for phase in &report.phases {
+                    eprintln!(
+                        "{:<16} {:>8.1}ms {:>+10} bytes",
+                        phase.name,
+                        phase.micros as f64 / 1000.0,
+                        phase.bytes
+                    );
+                }
+                eprintln!(
+                    "{:<16} {:>8.1}ms {:>+10} bytes, {} decls",
+                    "total",
+                    report.total_micros() as f64 / 1000.0,
+                    report.total_bytes(),
+                    report.decls
+                );
+            }
+        }
+    }
+    if let Some(provider) = args.secrets {
+        engine.add_secrets(SecretStore::new(provider))?;
+    } else if let Some(secrets) = &config.secrets {
+        engine.add_secrets(secrets.store())?;
+    }
+
+    if !args.lookups.is_empty() {
+        let mut tables = HashMap::new();
+        for (name, path) in &args.lookups {
+            tables.insert(name.clone(), LookupTable::load(path, &args.lookup_key)?);
+        }
+        engine.add_lookup_tables(tables)?;
+    }
+
+    #[cfg(feature = "geoip")]
+    if let Some(path) = &args.geoip_db {
+        engine.add_command(Box::new(mini_nu::geoip::GeoipLookup::open(path)?))?;
+    }
+    #[cfg(feature = "useragent")]
+    if let Some(path) = &args.ua_regexes {
+        engine.add_command(Box::new(mini_nu::useragent::UaParse::open(path)?))?;
+    }
+
+    if !args.patterns.is_empty() {
+        let mut patterns = HashMap::new();
+        for (name, regex) in &args.patterns {
+            let regex =
+                Regex::new(regex).map_err(|err| format!("Invalid --pattern '{name}': {err}"))?;
+            patterns.insert(name.clone(), regex);
+        }
+        engine.add_patterns(patterns)?;
+    }
 
-    let init_cwd = std::env::current_dir()?;
-    gather_parent_env_vars(&mut engine_state, init_cwd.as_ref());
+    if !args.datasets.is_empty() {
+        let mut datasets = Record::new();
+        for (name, path) in &args.datasets {
+            datasets.push(name, datasets::load(path, args.dataset_max_bytes)?);
+        }
+        engine.add_constant("datasets", Value::record(datasets, Span::unknown()))?;
+    }
 
-    let code_snippet = std::env::args().nth(1).expect("No code snippet provided");
+    if !args.args.is_empty() {
+        let mut named = Record::new();
+        for (key, value) in &args.args {
+            named.insert(key, Value::string(value, Span::unknown()));
+        }
+        engine.add_constant("args", Value::record(named, Span::unknown()))?;
+    }
 
-    let mut working_set = StateWorkingSet::new(&engine_state);
-    let block = nu_parser::parse(&mut working_set, None, code_snippet.as_bytes(), false);
+    if args.list_capabilities {
+        let capabilities = engine.capabilities();
+        let mut out = io::stdout().lock();
+        let written = match args.format {
+            Format::Json => writeln!(out, "{}", serde_json::to_string_pretty(&capabilities)?),
+            Format::Ndjson => writeln!(out, "{}", serde_json::to_string(&capabilities)?),
+            Format::Text => capabilities
+                .entries()
+                .into_iter()
+                .try_for_each(|(capability, commands)| match commands {
+                    [] => writeln!(out, "{capability}: none"),
+                    commands => writeln!(out, "{capability}: {}", commands.join(", ")),
+                })
+                .and_then(|()| {
+                    let host_env = if capabilities.host_env { "yes" } else { "no" };
+                    writeln!(out, "host_env: {host_env}")
+                }),
+        };
+        return Ok(on_epipe(written.and_then(|()| out.flush()), args.on_epipe)?);
+    }
 
-    engine_state.merge_delta(working_set.render())?;
+    let registry = match args.registry_dir.clone().or_else(Registry::default_dir) {
+        Some(dir) => Registry::new(dir),
+        None => Registry::new(REGISTRY_FALLBACK_DIR),
+    };
+    let mut code_file = args.closure_file.clone();
+    let code_snippet = match (args.code_snippet, &args.closure_file, &args.registry) {
+        (_, _, Some(RegistryCommand::Install(path))) => {
+            let name = registry.install(&engine, path, args.replace)?;
+            let mut out = io::stdout().lock();
+            let written = writeln!(out, "Installed {name}");
+            return Ok(on_epipe(written.and_then(|()| out.flush()), args.on_epipe)?);
+        }
+        (_, _, Some(RegistryCommand::List)) => {
+            let names = registry.list()?;
+            let mut out = io::stdout().lock();
+            let written = match args.format {
+                Format::Json => writeln!(out, "{}", serde_json::to_string_pretty(&names)?),
+                Format::Ndjson => writeln!(out, "{}", serde_json::to_string(&names)?),
+                Format::Text => names.iter().try_for_each(|name| writeln!(out, "{name}")),
+            };
+            return Ok(on_epipe(written.and_then(|()| out.flush()), args.on_epipe)?);
+        }
+        (None, None, Some(RegistryCommand::Run(name))) => {
+            let (path, source) = registry.get(name)?;
+            code_file = Some(path);
+            source
+        }
+        (Some(code_snippet), None, None) => code_snippet,
+        (None, Some(path), None) => std::fs::read_to_string(path)
+            .map_err(|err| format!("Can't read --closure-file {}: {err}", path.display()))?,
+        (None, None, None) => return Err("No code snippet provided".into()),
+        _ => return Err("Give only one of a code snippet, --closure-file or run <name>".into()),
+    };
 
-    let mut stack = Stack::new();
+    if args.lint {
+        let findings = lint::lint(&engine, &code_snippet, &args.rules);
+        let mut out = io::stdout().lock();
+        let written = match args.format {
+            Format::Json => writeln!(out, "{}", serde_json::to_string_pretty(&findings)?),
+            Format::Ndjson => findings.iter().try_for_each(|finding| {
+                serde_json::to_writer(&mut out, finding)?;
+                writeln!(out)
+            }),
+            Format::Text => findings.iter().try_for_each(|finding| {
+                writeln!(
+                    out,
+                    "{}:{}: {:?} [{}] {}",
+                    finding.line, finding.column, finding.severity, finding.rule, finding.message
+                )
+            }),
+        };
+        on_epipe(written.and_then(|()| out.flush()), args.on_epipe)?;
+        if findings.iter().any(|f| f.severity == Severity::Error) {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
 
-    match eval_block_with_early_return::<WithoutDebug>(
-        &engine_state,
-        &mut stack,
-        &block,
-        PipelineData::empty(),
+    let expect = match &args.expect {
+        Some(source) => Some(engine.parse_closure(source)?),
+        None => None,
+    };
+    let post = match &args.post {
+        Some(source) => Some(engine.parse_closure(source)?),
+        None => None,
+    };
+    let on_error = match &args.on_error {
+        Some(source) => Some(engine.parse_closure(source)?),
+        None => None,
+    };
+    let notify = match (
+        &config.notify,
+        args.notify_on_failure || args.notify_on_complete,
     ) {
-        Ok(pipeline_data) => {
-            // Handle successful execution
-            match pipeline_data.into_value(Span::test_data()) {
-                Ok(value) => match value {
-                    Value::String { val, .. } => println!("{}", val),
-                    Value::List { vals, .. } => {
-                        for val in vals {
-                            println!("{:?}", val);
+        (_, false) => None,
+        (None, true) => return Err("--notify needs a [notify] table in the config".into()),
+        (Some(notify), true) => {
+            let template = match &notify.template {
+                Some(source) => Some(engine.parse_closure(source)?),
+                None => None,
+            };
+            Some((&notify.sink, template))
+        }
+    };
+
+    let mut stdin_format = args.stdin_format;
+    if let Some(size) = &args.chunk_size {
+        let Some(InputFormat::Bytes { chunk_size }) = &mut stdin_format else {
+            return Err("--chunk-size needs --stdin-format bytes".into());
+        };
+        *chunk_size = match engine.eval_value(size, PipelineData::empty())? {
+            Value::Filesize { val, .. } if val > 0 => val as usize,
+            other => {
+                return Err(format!(
+                    "Invalid --chunk-size '{size}': expected a positive filesize, got {}",
+                    other.get_type()
+                )
+                .into())
+            }
+        };
+    }
+    let input = match stdin_format {
+        Some(format) => {
+            let malformed: Option<Malformed> = match args.malformed_lines.as_deref() {
+                Some("stderr") => Some(Box::new(io::stderr())),
+                Some(path) => Some(Box::new(
+                    std::fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(path)
+                        .map_err(|err| format!("Can't open --malformed-lines '{path}': {err}"))?,
+                )),
+                None => None,
+            };
+            if args.stream {
+                let signals = engine.state.signals().clone();
+                let stream = input::stream(
+                    io::stdin(),
+                    format,
+                    args.stdin_delimiter,
+                    malformed,
+                    engine.clock(),
+                    signals,
+                )?;
+                PipelineData::ListStream(stream, None)
+            } else {
+                let value = input::read(
+                    io::stdin(),
+                    format,
+                    args.stdin_delimiter,
+                    malformed,
+                    engine.clock(),
+                )?;
+                PipelineData::Value(value, None)
+            }
+        }
+        None if args.stream => return Err("--stream needs a --stdin-format".into()),
+        None => PipelineData::empty(),
+    };
+
+    let dup_key = match &args.dup_key {
+        Some(source) => Some(engine.parse_closure(source)?),
+        None => None,
+    };
+    let tees = args
+        .tees
+        .iter()
+        .map(|(name, source)| Tee::new(&mut engine, name, source))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut duplicates = match &args.suppress_duplicates {
+        Some(window) => match engine.eval_value(window, PipelineData::empty())? {
+            Value::Duration { val, .. } if val > 0 => {
+                Some(DuplicateWindow::new(Duration::from_nanos(val as u64)))
+            }
+            other => {
+                return Err(format!(
+                "Invalid --suppress-duplicates '{window}': expected a positive duration, got {}",
+                other.get_type()
+            )
+                .into())
+            }
+        },
+        None => None,
+    };
+
+    let started = Instant::now();
+    let mut index = 0;
+    let mut eval_error = None;
+    let mut hit_limit = false;
+    let mut failures = vec![];
+    let mut rows = vec![];
+    let mut left_out = 0;
+    let evaluated = match &code_file {
+        Some(path) => engine.eval_file(&path.to_string_lossy(), &code_snippet, input),
+        None => engine.eval(&code_snippet, input),
+    };
+    match evaluated {
+        Ok(mut pipeline_data) => {
+            // Closures need values, so raw byte streams are collected when there's one to run.
+            if (expect.is_some()
+                || post.is_some()
+                || duplicates.is_some()
+                || args.report.is_some()
+                || !tees.is_empty())
+                && matches!(pipeline_data, PipelineData::ByteStream(..))
+            {
+                match pipeline_data.into_value(Span::test_data()) {
+                    Ok(value) => pipeline_data = PipelineData::Value(value, None),
+                    Err(err) => {
+                        eprintln!("Error converting pipeline data: {:?}", err);
+                        pipeline_data = PipelineData::Empty;
+                    }
+                }
+            }
+
+            // With a report to write instead, the results themselves go nowhere.
+            let mut out: Box<dyn Write> = match args.report {
+                Some(_) => Box::new(io::sink()),
+                None => Box::new(io::stdout().lock()),
+            };
+            let written = write_pipeline(&mut out, pipeline_data, args.format, |result| {
+                let result = match &post {
+                    Some(post) => {
+                        let index = Value::int(index, result.span());
+                        apply_closure(&engine, post, vec![result.clone(), index], result)
+                    }
+                    None => result,
+                };
+                index += 1;
+                if let Some(duplicates) = &mut duplicates {
+                    let key = match &dup_key {
+                        Some(dup_key) => {
+                            apply_closure(&engine, dup_key, vec![result.clone()], result.clone())
                         }
+                        None => result.clone(),
+                    };
+                    if duplicates.is_duplicate(value_to_json(&key).to_string()) {
+                        return vec![];
                     }
-                    other => println!("{:?}", other),
-                },
-                Err(err) => eprintln!("Error converting pipeline data: {:?}", err),
+                }
+                if let Some(expect) = &expect {
+                    failures.extend(unmet_expectation(&engine, expect, &result));
+                }
+                let results = match tees.as_slice() {
+                    [] => vec![result],
+                    tees => tee(tees, result),
+                };
+                if args.report.is_some() {
+                    for result in results {
+                        if rows.len() < REPORT_MAX_ROWS {
+                            rows.push(result);
+                        } else {
+                            left_out += 1;
+                        }
+                    }
+                    return vec![];
+                }
+                results
+            });
+            // A stream cut short by one of the limits just ends, so check for that once it's
+            // drained, before the report's evaluation starts them over.
+            let limited = engine.limit_exceeded();
+            let written = match (written, &args.report) {
+                (Ok(()), Some(report)) if limited.is_none() => {
+                    if left_out > 0 {
+                        eprintln!(
+                            "Report covers the first {REPORT_MAX_ROWS} results, {left_out} left out"
+                        );
+                    }
+                    let rows = Value::list(rows, Span::unknown());
+                    match engine.eval(report, PipelineData::Value(rows, None)) {
+                        Ok(report) => {
+                            write_pipeline(&mut io::stdout().lock(), report, args.format, |value| {
+                                vec![value]
+                            })
+                        }
+                        Err(error) => {
+                            eprintln!("Error: {:?}", error);
+                            Ok(())
+                        }
+                    }
+                }
+                (written, _) => written,
+            };
+            on_epipe(written, args.on_epipe)?;
+            if let Some(error) = limited {
+                eprintln!("Error: {:?}", error);
+                eval_error = Some(error.to_string());
+                hit_limit = true;
             }
         }
         Err(error) => {
-            eprintln!("Error: {:?}", error);
+            // A snippet that fails outright satisfies no expectation.
+            if expect.is_some() {
+                failures.push(format!("evaluation failed: {error}"));
+            }
+            eval_error = Some(error.to_string());
+            // The snippet can raise an error that reads like a limit's, so ask the engine.
+            hit_limit = engine.exceeded_limit().is_some();
+            match &on_error {
+                Some(on_error) => {
+                    let report = failure_report(&code_snippet, &error);
+                    match apply_closure(&engine, on_error, vec![report.clone()], report) {
+                        Value::Nothing { .. } => {}
+                        report => write_result(
+                            &mut io::stderr().lock(),
+                            report,
+                            args.format,
+                            &mut |value| vec![value],
+                        )?,
+                    }
+                }
+                // Errors in a file are rendered, so they show where in the file they happened.
+                None if code_file.is_some() => eprintln!(
+                    "{}",
+                    format_error(&StateWorkingSet::new(&engine.state), &error)
+                ),
+                None => eprintln!("Error: {:?}", error),
+            }
+        }
+    }
+
+    if let Some((sink, template)) = &notify {
+        let failed = eval_error.is_some() || !failures.is_empty();
+        if failed || args.notify_on_complete {
+            let status = if failed { "failed" } else { "completed" };
+            let message = match template {
+                Some(template) => {
+                    let span = Span::unknown();
+                    let summary = Value::record(
+                        record! {
+                            "status" => Value::string(status, span),
+                            "source" => Value::string(&code_snippet, span),
+                            "results" => Value::int(index, span),
+                            "unmet_expectations" => Value::int(failures.len() as i64, span),
+                            "error" => eval_error
+                                .map_or(Value::nothing(span), |err| Value::string(err, span)),
+                            "elapsed" => Value::duration(started.elapsed().as_nanos() as i64, span),
+                        },
+                        span,
+                    );
+                    apply_closure(&engine, template, vec![summary.clone()], summary)
+                        .coerce_into_string()?
+                }
+                None => {
+                    let mut message = format!("mini-nu run {status}: {index} results");
+                    if !failures.is_empty() {
+                        message.push_str(&format!(", {} failed --expect", failures.len()));
+                    }
+                    if let Some(error) = eval_error {
+                        message.push_str(&format!("\n{error}"));
+                    }
+                    message
+                }
+            };
+            if let Err(err) = sink.send(&message) {
+                eprintln!("Notification failed: {err}");
+            }
+        }
+    }
+
+    if hit_limit {
+        std::process::exit(1);
+    }
+    if !failures.is_empty() {
+        for failure in &failures {
+            eprintln!("Expectation failed: {failure}");
         }
+        eprintln!("{} result(s) failed --expect", failures.len());
+        std::process::exit(1);
     }
 
     Ok(())
 }
+
+/// Apply `policy` if writing failed because the reader of stdout went away (e.g. `| head`); there's
+/// no one left to tell.
+fn on_epipe(written: io::Result<()>, policy: OnEpipe) -> io::Result<()> {
+    match written {
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => match policy {
+            OnEpipe::Exit => std::process::exit(EPIPE_EXIT_CODE),
+            OnEpipe::Ignore => Ok(()),
+        },
+        written => written,
+    }
+}
+
+/// Run `closure` on `value` with `args` for its positionals, turning a failure into an error
+/// value.
+fn apply_closure(engine: &Engine, closure: &Closure, args: Vec<Value>, value: Value) -> Value {
+    let span = value.span();
+    engine
+        .eval_closure_with_args(closure, args, PipelineData::Value(value, None))
+        .and_then(|data| data.into_value(span))
+        .unwrap_or_else(|err| Value::error(err, span))
+}
+
+/// Describe a failed evaluation for the `--on-error` closure.
+fn failure_report(source: &str, error: &ShellError) -> Value {
+    let span = Span::unknown();
+    Value::record(
+        record! {
+            "source" => Value::string(source, span),
+            "error" => Value::string(error.to_string(), span),
+            "debug" => Value::string(format!("{error:?}"), span),
+        },
+        span,
+    )
+}
+
+/// Check a result against the `--expect` predicate, describing it if it doesn't satisfy it.
+fn unmet_expectation(engine: &Engine, expect: &Closure, result: &Value) -> Option<String> {
+    let verdict = engine
+        .eval_closure_with_value(expect, result.clone())
+        .and_then(|data| data.into_value(Span::unknown()));
+    match verdict {
+        Ok(Value::Bool { val: true, .. }) => None,
+        Ok(Value::Bool { val: false, .. }) => Some(format!("{result:?}")),
+        Ok(other) => Some(format!(
+            "{result:?}: predicate returned {} instead of bool",
+            other.get_type()
+        )),
+        Err(err) => Some(format!("{result:?}: {err:?}")),
+    }
+}
+
+/// Write each result to `out` as soon as it's produced, passing it through `process` first, which
+/// can drop it or turn it into several. The items of a list are results of their own, though in
+/// JSON they are still written as one array.
+fn write_pipeline(
+    out: &mut impl Write,
+    data: PipelineData,
+    format: Format,
+    mut process: impl FnMut(Value) -> Vec<Value>,
+) -> io::Result<()> {
+    match data {
+        PipelineData::Empty => {}
+        PipelineData::Value(Value::List { vals, .. }, ..) => {
+            write_items(out, vals.into_iter(), format, &mut process)?
+        }
+        PipelineData::Value(value, ..) => write_result(out, value, format, &mut process)?,
+        PipelineData::ListStream(stream, ..) => {
+            write_items(out, stream.into_iter(), format, &mut process)?
+        }
+        PipelineData::ByteStream(stream, ..) => match format {
+            Format::Text => {
+                if let Some(mut reader) = stream.reader() {
+                    io::copy(&mut reader, out)?;
+                }
+            }
+            Format::Json | Format::Ndjson => {
+                let value = stream
+                    .into_value()
+                    .map_err(|err| io::Error::other(format!("{err:?}")))?;
+                write_result(out, value, format, &mut process)?;
+            }
+        },
+    }
+    out.flush()
+}
+
+fn write_result(
+    out: &mut impl Write,
+    value: Value,
+    format: Format,
+    process: &mut impl FnMut(Value) -> Vec<Value>,
+) -> io::Result<()> {
+    let value = match <[Value; 1]>::try_from(process(value)) {
+        Ok([value]) => value,
+        Err(values) => {
+            return write_items(out, values.into_iter(), format, &mut |value| vec![value])
+        }
+    };
+    match (format, value) {
+        (Format::Text, Value::String { val, .. }) => writeln!(out, "{}", val),
+        (Format::Text, other) => writeln!(out, "{:?}", other),
+        (Format::Json | Format::Ndjson, value) => {
+            serde_json::to_writer(&mut *out, &value_to_json(&value))?;
+            writeln!(out)
+        }
+    }
+}
+
+fn write_items(
+    out: &mut impl Write,
+    items: impl Iterator<Item = Value>,
+    format: Format,
+    process: &mut impl FnMut(Value) -> Vec<Value>,
+) -> io::Result<()> {
+    let mut empty = true;
+    for val in items.flat_map(process) {
+        match format {
+            Format::Text => writeln!(out, "{:?}", val)?,
+            Format::Ndjson => {
+                serde_json::to_writer(&mut *out, &value_to_json(&val))?;
+                writeln!(out)?;
+            }
+            Format::Json => {
+                out.write_all(if empty { b"[\n  " } else { b",\n  " })?;
+                serde_json::to_writer(&mut *out, &value_to_json(&val))?;
+            }
+        }
+        empty = false;
+    }
+    match format {
+        Format::Text | Format::Ndjson => Ok(()),
+        Format::Json if empty => writeln!(out, "[]"),
+        Format::Json => writeln!(out, "\n]"),
+    }
+}
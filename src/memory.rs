@@ -0,0 +1,90 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static CEILING: AtomicUsize = AtomicUsize::new(usize::MAX);
+static EXCEEDED: AtomicBool = AtomicBool::new(false);
+static INTERRUPT: Mutex<Option<Arc<AtomicBool>>> = Mutex::new(None);
+
+/// The system allocator, counting live heap bytes so [`Engine::limit_memory`] can enforce a
+/// ceiling. It has to be installed by the binary for the limit to have any effect:
+///
+/// ```no_run
+/// #[global_allocator]
+/// static ALLOCATOR: mini_nu::memory::CountingAllocator = mini_nu::memory::CountingAllocator;
+/// ```
+///
+/// Going over the ceiling doesn't fail the allocation, which would abort the process; it trips
+/// the watching engine's interrupt instead, so evaluation stops at the next check and reports it.
+///
+/// [`Engine::limit_memory`]: crate::Engine::limit_memory
+pub struct CountingAllocator;
+
+impl CountingAllocator {
+    fn grew(size: usize) {
+        let allocated = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+        if allocated > CEILING.load(Ordering::Relaxed) && !EXCEEDED.swap(true, Ordering::Relaxed) {
+            // `try_lock`, as whoever holds the lock may be the one allocating.
+            if let Ok(interrupt) = INTERRUPT.try_lock() {
+                if let Some(interrupt) = interrupt.as_ref() {
+                    interrupt.store(true, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            Self::grew(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            Self::grew(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            if new_size > layout.size() {
+                Self::grew(new_size - layout.size());
+            } else {
+                ALLOCATED.fetch_sub(layout.size() - new_size, Ordering::Relaxed);
+            }
+        }
+        new_ptr
+    }
+}
+
+/// Live heap bytes, as counted by [`CountingAllocator`]; zero if it isn't installed.
+pub fn allocated() -> usize {
+    ALLOCATED.load(Ordering::Relaxed)
+}
+
+/// Trip `interrupt` once the heap grows more than `limit` bytes past its current size. There is
+/// one ceiling per process, shared by every thread; watching again replaces it, so callers arm
+/// it once per run rather than per evaluation.
+pub(crate) fn watch(limit: usize, interrupt: Arc<AtomicBool>) {
+    *INTERRUPT.lock().expect("memory watch lock poisoned") = Some(interrupt);
+    EXCEEDED.store(false, Ordering::Relaxed);
+    CEILING.store(allocated().saturating_add(limit), Ordering::Relaxed);
+}
+
+/// Whether the heap has gone over the ceiling since the last [`watch`].
+pub(crate) fn exceeded() -> bool {
+    EXCEEDED.load(Ordering::Relaxed)
+}
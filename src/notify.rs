@@ -0,0 +1,55 @@
+use std::io::Write;
+use std::process::{Command as Process, Stdio};
+
+use serde::Deserialize;
+
+/// Where `--notify` sends the outcome of a run.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "sink", rename_all = "lowercase", deny_unknown_fields)]
+pub enum NotifySink {
+    /// Standard error of the run itself.
+    Stderr,
+    /// The stdin of `{program} {args...}`, e.g. `sendmail ops@example.com`.
+    #[serde(rename = "cmd")]
+    Command {
+        program: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+    /// A Slack incoming webhook.
+    #[cfg(feature = "slack")]
+    Slack { webhook: String },
+}
+
+impl NotifySink {
+    pub fn send(&self, message: &str) -> Result<(), String> {
+        match self {
+            Self::Stderr => {
+                eprintln!("{message}");
+                Ok(())
+            }
+            Self::Command { program, args } => {
+                let mut child = Process::new(program)
+                    .args(args)
+                    .stdin(Stdio::piped())
+                    .spawn()
+                    .map_err(|err| format!("Can't run {program}: {err}"))?;
+                if let Some(mut stdin) = child.stdin.take() {
+                    stdin
+                        .write_all(message.as_bytes())
+                        .map_err(|err| format!("Can't write to {program}: {err}"))?;
+                }
+                let status = child.wait().map_err(|err| err.to_string())?;
+                if !status.success() {
+                    return Err(format!("{program} exited with {status}"));
+                }
+                Ok(())
+            }
+            #[cfg(feature = "slack")]
+            Self::Slack { webhook } => ureq::post(webhook)
+                .send_json(serde_json::json!({ "text": message }))
+                .map(|_| ())
+                .map_err(|err| format!("Can't post to Slack: {err}")),
+        }
+    }
+}
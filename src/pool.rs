@@ -0,0 +1,76 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+
+use crate::Engine;
+
+/// Engines forked ahead of time from one template, so concurrent evaluations each get their own
+/// without paying for the fork on the hot path.
+///
+/// A checked-out engine goes back to the pool when its [`PooledEngine`] is dropped. Every
+/// evaluation already runs on a fresh stack, so nothing a snippet does to `$env` or its variables
+/// carries over to the next user of the engine. What does carry over is whatever got parsed into
+/// the engine while it was out, which only adds to it.
+///
+/// The memory limit is process-wide, so pooled engines running at the same time share it.
+pub struct EnginePool {
+    template: Engine,
+    idle: Mutex<Vec<Engine>>,
+}
+
+impl EnginePool {
+    /// Fork `size` engines from `template` up front.
+    pub fn new(template: Engine, size: usize) -> Self {
+        let idle = (0..size).map(|_| template.fork()).collect();
+        Self {
+            template,
+            idle: Mutex::new(idle),
+        }
+    }
+
+    /// Take an idle engine, or fork a new one from the template if none is left.
+    pub fn checkout(&self) -> PooledEngine<'_> {
+        let engine = self
+            .idle
+            .lock()
+            .expect("engine pool lock poisoned")
+            .pop()
+            .unwrap_or_else(|| self.template.fork());
+        PooledEngine {
+            pool: self,
+            engine: Some(engine),
+        }
+    }
+
+    /// How many engines are waiting to be checked out.
+    pub fn idle(&self) -> usize {
+        self.idle.lock().expect("engine pool lock poisoned").len()
+    }
+}
+
+/// An [`Engine`] checked out of an [`EnginePool`], returned to it on drop.
+pub struct PooledEngine<'a> {
+    pool: &'a EnginePool,
+    engine: Option<Engine>,
+}
+
+impl Deref for PooledEngine<'_> {
+    type Target = Engine;
+
+    fn deref(&self) -> &Engine {
+        self.engine.as_ref().expect("engine is only taken on drop")
+    }
+}
+
+impl DerefMut for PooledEngine<'_> {
+    fn deref_mut(&mut self) -> &mut Engine {
+        self.engine.as_mut().expect("engine is only taken on drop")
+    }
+}
+
+impl Drop for PooledEngine<'_> {
+    fn drop(&mut self) {
+        if let (Some(engine), Ok(mut idle)) = (self.engine.take(), self.pool.idle.lock()) {
+            idle.push(engine);
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use nu_command::*;
+use nu_protocol::engine::Command;
+
+use crate::sandbox::AbsoluteIntoDatetime;
+
+/// A curated group of `nu-command` declarations, for building sandboxes up from the language core
+/// rather than down from the full shell. None of them touch the filesystem, network or processes.
+/// `into datetime` is the [`AbsoluteIntoDatetime`] variant, which refuses "now" and other relative
+/// dates, so the clock is out of reach too, save for `format date` filling in today's date when
+/// given a string with only a time of day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    /// Selecting, reshaping and iterating over lists and tables.
+    Filters,
+    /// Splitting, matching and transforming strings.
+    Strings,
+    /// Arithmetic and statistics over numbers.
+    Math,
+    /// Converting between types and parsing or serializing text formats.
+    Data,
+}
+
+impl std::str::FromStr for Profile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "filters" => Ok(Self::Filters),
+            "strings" => Ok(Self::Strings),
+            "math" => Ok(Self::Math),
+            "data" => Ok(Self::Data),
+            _ => Err(format!(
+                "Unknown profile '{s}', expected filters, strings, math or data"
+            )),
+        }
+    }
+}
+
+impl Profile {
+    pub fn commands(self) -> Vec<Box<dyn Command>> {
+        match self {
+            Self::Filters => vec![
+                Box::new(All),
+                Box::new(Any),
+                Box::new(Append),
+                Box::new(Columns),
+                Box::new(Compact),
+                Box::new(Default),
+                Box::new(Drop),
+                Box::new(DropColumn),
+                Box::new(Each),
+                Box::new(Enumerate),
+                Box::new(Filter),
+                Box::new(Find),
+                Box::new(First),
+                Box::new(Flatten),
+                Box::new(Get),
+                Box::new(GroupBy),
+                Box::new(Insert),
+                Box::new(IsEmpty),
+                Box::new(IsNotEmpty),
+                Box::new(Items),
+                Box::new(Last),
+                Box::new(Length),
+                Box::new(Merge),
+                Box::new(Move),
+                Box::new(Prepend),
+                Box::new(Range),
+                Box::new(Reduce),
+                Box::new(Reject),
+                Box::new(Rename),
+                Box::new(Reverse),
+                Box::new(Select),
+                Box::new(Skip),
+                Box::new(SkipUntil),
+                Box::new(SkipWhile),
+                Box::new(Sort),
+                Box::new(SortBy),
+                Box::new(Take),
+                Box::new(TakeUntil),
+                Box::new(TakeWhile),
+                Box::new(Transpose),
+                Box::new(Uniq),
+                Box::new(UniqBy),
+                Box::new(Update),
+                Box::new(Upsert),
+                Box::new(Values),
+                Box::new(Where),
+                Box::new(Wrap),
+                Box::new(Zip),
+            ],
+            Self::Strings => vec![
+                Box::new(Char),
+                Box::new(DetectColumns),
+                Box::new(Format),
+                Box::new(Lines),
+                Box::new(Parse),
+                Box::new(Split),
+                Box::new(SplitChars),
+                Box::new(SplitColumn),
+                Box::new(SplitRow),
+                Box::new(SplitWords),
+                Box::new(Str),
+                Box::new(StrCapitalize),
+                Box::new(StrContains),
+                Box::new(StrDistance),
+                Box::new(StrDowncase),
+                Box::new(StrEndswith),
+                Box::new(StrExpand),
+                Box::new(StrIndexOf),
+                Box::new(StrJoin),
+                Box::new(StrLength),
+                Box::new(StrReplace),
+                Box::new(StrReverse),
+                Box::new(StrStartsWith),
+                Box::new(StrSubstring),
+                Box::new(StrTrim),
+                Box::new(StrUpcase),
+            ],
+            Self::Math => vec![
+                Box::new(Math),
+                Box::new(MathAbs),
+                Box::new(MathAvg),
+                Box::new(MathCeil),
+                Box::new(MathFloor),
+                Box::new(MathLog),
+                Box::new(MathMax),
+                Box::new(MathMedian),
+                Box::new(MathMin),
+                Box::new(MathMode),
+                Box::new(MathProduct),
+                Box::new(MathRound),
+                Box::new(MathSqrt),
+                Box::new(MathStddev),
+                Box::new(MathSum),
+                Box::new(MathVariance),
+            ],
+            Self::Data => vec![
+                Box::new(FormatDate),
+                Box::new(FormatDuration),
+                Box::new(FormatFilesize),
+                Box::new(From),
+                Box::new(FromCsv),
+                Box::new(FromJson),
+                Box::new(FromNuon),
+                Box::new(FromToml),
+                Box::new(FromTsv),
+                Box::new(FromXml),
+                Box::new(FromYaml),
+                Box::new(Into),
+                Box::new(IntoBool),
+                Box::new(AbsoluteIntoDatetime),
+                Box::new(IntoDuration),
+                Box::new(IntoFilesize),
+                Box::new(IntoFloat),
+                Box::new(IntoInt),
+                Box::new(IntoRecord),
+                Box::new(IntoString),
+                Box::new(To),
+                Box::new(ToCsv),
+                Box::new(ToJson),
+                Box::new(ToNuon),
+                Box::new(ToText),
+                Box::new(ToToml),
+                Box::new(ToTsv),
+                Box::new(ToXml),
+                Box::new(ToYaml),
+            ],
+        }
+    }
+}
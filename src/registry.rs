@@ -0,0 +1,129 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use nu_protocol::engine::StateWorkingSet;
+use nu_protocol::ShellError;
+
+use crate::sandbox::denied_calls;
+use crate::Engine;
+
+/// Closures installed as `<name>.nu` files in a directory, to be run by name.
+pub struct Registry {
+    dir: PathBuf,
+}
+
+impl Registry {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// `$XDG_CONFIG_HOME/mini-nu/closures`, falling back to `~/.config/mini-nu/closures`.
+    pub fn default_dir() -> Option<PathBuf> {
+        let config = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(config.join("mini-nu").join("closures"))
+    }
+
+    /// Copy the closure at `path` into the registry under its file stem, once `engine` accepts
+    /// it (see [`validate`]). A closure already installed under that name is only overwritten
+    /// with `replace`. Returns the name it was installed as.
+    pub fn install(&self, engine: &Engine, path: &Path, replace: bool) -> Result<String, String> {
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .ok_or_else(|| format!("Can't name a closure after {}", path.display()))?;
+        check_name(name)?;
+        let source = std::fs::read_to_string(path)
+            .map_err(|err| format!("Can't read {}: {err}", path.display()))?;
+        validate(engine, name, &source)?;
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|err| format!("Can't create {}: {err}", self.dir.display()))?;
+        let target = self.path(name);
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .create_new(!replace)
+            .open(&target);
+        file.and_then(|mut file| file.write_all(source.as_bytes()))
+            .map_err(|err| match err.kind() {
+                std::io::ErrorKind::AlreadyExists => {
+                    format!("A closure named '{name}' is already installed")
+                }
+                _ => format!("Can't write {}: {err}", target.display()),
+            })?;
+        Ok(name.to_string())
+    }
+
+    /// Names of the installed closures, sorted.
+    pub fn list(&self) -> Result<Vec<String>, String> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(format!("Can't read {}: {err}", self.dir.display())),
+        };
+        let mut names = vec![];
+        for entry in entries {
+            let path = entry.map_err(|err| err.to_string())?.path();
+            if path.extension().is_some_and(|ext| ext == "nu") {
+                if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// The file and source of the closure installed as `name`.
+    pub fn get(&self, name: &str) -> Result<(PathBuf, String), String> {
+        check_name(name)?;
+        let path = self.path(name);
+        let source = std::fs::read_to_string(&path).map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => format!("No closure named '{name}' is installed"),
+            _ => format!("Can't read {}: {err}", path.display()),
+        })?;
+        Ok((path, source))
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{name}.nu"))
+    }
+}
+
+/// Refuse names that are empty, hidden or lead out of the registry directory.
+fn check_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(format!("Invalid closure name '{name}'"));
+    }
+    Ok(())
+}
+
+/// Check that `source` parses in `engine` and calls nothing its policy denies, so a closure
+/// installed for one profile doesn't only fail once it runs. `name` labels it in errors.
+pub fn validate(engine: &Engine, name: &str, source: &str) -> Result<(), String> {
+    // Every denied command is listed, where parsing in the engine stops at the first.
+    let mut working_set = StateWorkingSet::new(&engine.state);
+    let block = nu_parser::parse(&mut working_set, Some(name), source.as_bytes(), false);
+    let mut uses: Vec<String> = vec![];
+    for (decl_name, _, _) in denied_calls(&working_set, &block) {
+        if !uses.contains(&decl_name) {
+            uses.push(decl_name);
+        }
+    }
+    if !uses.is_empty() {
+        return Err(format!(
+            "{name} uses commands the policy denies: {}",
+            uses.join(", ")
+        ));
+    }
+
+    // Parsing in a fork also applies the engine's checks on syntax, like file redirection.
+    engine.fork().parse(source).map_err(|err| match err {
+        ShellError::GenericError { error, msg, .. } => format!("{name}: {error}: {msg}"),
+        err => format!("{name}: {err}"),
+    })?;
+    Ok(())
+}
@@ -0,0 +1,598 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset};
+use nu_engine::command_prelude::*;
+use nu_parser::{flatten_block, FlatShape};
+use nu_protocol::ast::Block;
+use nu_protocol::engine::StateWorkingSet;
+
+use crate::engine::{Limit, PARSE_ERROR, POLICY_ERROR};
+use crate::profiles::Profile;
+use crate::{Engine, EngineBuilder};
+
+/// Commands whose output depends on the host, the clock or a random source, plus the ones that
+/// start, signal or limit processes, which could depend on or change any of those.
+pub const NONDETERMINISTIC_COMMANDS: &[&str] = &[
+    "random",
+    "random bool",
+    "random chars",
+    "random dice",
+    "random float",
+    "random int",
+    "random uuid",
+    "date now",
+    "date humanize",
+    "timeit",
+    "seq date",
+    "sys",
+    "sys cpu",
+    "sys disks",
+    "sys host",
+    "sys mem",
+    "sys net",
+    "sys temp",
+    "sys users",
+    "ps",
+    "port",
+    "uname",
+    "whoami",
+    "term size",
+    "run-external",
+    "exec",
+    "start",
+    "kill",
+    "ulimit",
+];
+
+/// Commands that create, modify or remove files, plus the ones that start, signal or limit
+/// processes, which could do any of that.
+pub const FILESYSTEM_WRITE_COMMANDS: &[&str] = &[
+    "save",
+    "rm",
+    "mv",
+    "cp",
+    "touch",
+    "mkdir",
+    "mktemp",
+    "config reset",
+    "run-external",
+    "exec",
+    "start",
+    "kill",
+    "ulimit",
+];
+
+/// Commands that talk to the network, plus the ones that start, signal or limit processes,
+/// which could too.
+pub const NETWORK_COMMANDS: &[&str] = &[
+    "http",
+    "http delete",
+    "http get",
+    "http head",
+    "http options",
+    "http patch",
+    "http post",
+    "http put",
+    "port",
+    "run-external",
+    "exec",
+    "start",
+    "kill",
+    "ulimit",
+];
+
+/// Commands that read, write or navigate the filesystem, plus the ones that start, signal or
+/// limit processes, which could too.
+pub const FILESYSTEM_COMMANDS: &[&str] = &[
+    "cd",
+    "config reset",
+    "cp",
+    "du",
+    "glob",
+    "ls",
+    "mkdir",
+    "mktemp",
+    "mv",
+    "nu-check",
+    "open",
+    "path exists",
+    "path expand",
+    "path type",
+    "rm",
+    "save",
+    "touch",
+    "watch",
+    "which",
+    "run-external",
+    "exec",
+    "start",
+    "kill",
+    "ulimit",
+];
+
+/// The category [`Denied`] stubs are registered under.
+pub const DENIED_CATEGORY: &str = "denied";
+
+/// What follows the quoted command name in a [`Denied`] error.
+const DENIED_SUFFIX: &str = " is not available";
+
+/// Stands in for a command that has been taken away, so callers get a clear error at run time
+/// instead of "command not found".
+#[derive(Clone)]
+pub struct Denied {
+    name: String,
+    reason: String,
+}
+
+impl Denied {
+    pub fn new(name: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl Command for Denied {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(&self.name)
+            .input_output_types(vec![(Type::Any, Type::Any)])
+            .allows_unknown_args()
+            .category(Category::Custom(DENIED_CATEGORY.into()))
+    }
+
+    fn usage(&self) -> &str {
+        &self.reason
+    }
+
+    fn run(
+        &self,
+        _engine_state: &EngineState,
+        _stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        Err(denied_error(&self.name, &self.reason, call.head))
+    }
+}
+
+/// The error a call to the denied command `name` fails with.
+pub(crate) fn denied_error(name: &str, reason: &str, span: Span) -> ShellError {
+    ShellError::GenericError {
+        error: format!("`{name}`{DENIED_SUFFIX}"),
+        msg: reason.into(),
+        span: Some(span),
+        help: None,
+        inner: vec![],
+    }
+}
+
+/// Every call in `block`, the blocks nested in it included, to a command that is [`Denied`]: its
+/// name, the reason it's denied and where it's called. Running an external command counts as
+/// calling `run-external`.
+pub(crate) fn denied_calls(
+    working_set: &StateWorkingSet,
+    block: &Block,
+) -> Vec<(String, String, Span)> {
+    let denied = |decl_id| {
+        let decl = working_set.get_decl(decl_id);
+        (decl.signature().category == Category::Custom(DENIED_CATEGORY.into()))
+            .then(|| (decl.name().to_string(), decl.usage().to_string()))
+    };
+    flatten_block(working_set, block)
+        .into_iter()
+        .filter_map(|(span, shape)| {
+            let (name, reason) = match shape {
+                FlatShape::InternalCall(decl_id) => denied(decl_id)?,
+                FlatShape::External => denied(working_set.find_decl(b"run-external")?)?,
+                _ => return None,
+            };
+            Some((name, reason, span))
+        })
+        .collect()
+}
+
+/// A `date now` that always returns the same instant.
+#[derive(Clone)]
+pub struct FrozenDateNow {
+    now: DateTime<FixedOffset>,
+}
+
+impl FrozenDateNow {
+    pub fn new(now: DateTime<FixedOffset>) -> Self {
+        Self { now }
+    }
+}
+
+impl Command for FrozenDateNow {
+    fn name(&self) -> &str {
+        "date now"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("date now")
+            .input_output_types(vec![(Type::Nothing, Type::Date)])
+            .category(Category::Date)
+    }
+
+    fn usage(&self) -> &str {
+        "Get the (frozen) current date."
+    }
+
+    fn run(
+        &self,
+        _engine_state: &EngineState,
+        _stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        Ok(Value::date(self.now, call.head).into_pipeline_data())
+    }
+}
+
+/// An `into datetime` that refuses strings whose date depends on when they're parsed, like "now",
+/// "yesterday" or a bare time of day, as parsing those reads the clock. Dates given in full, Unix
+/// timestamps and `--format` parsing work as usual.
+#[derive(Clone)]
+pub struct AbsoluteIntoDatetime;
+
+impl AbsoluteIntoDatetime {
+    fn check(value: &Value) -> Result<(), ShellError> {
+        match value {
+            Value::String { val, .. } if is_relative_date(val) => Err(ShellError::GenericError {
+                error: "Relative dates are disabled".into(),
+                msg: format!("'{val}' depends on the current time"),
+                span: Some(value.span()),
+                help: Some("give the date in full, e.g. 2024-01-31T12:00:00+00:00".into()),
+                inner: vec![],
+            }),
+            Value::List { vals, .. } => vals.iter().try_for_each(Self::check),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Whether `text` comes out as a different date depending on the day it's parsed on.
+fn is_relative_date(text: &str) -> bool {
+    if text.trim().parse::<i64>().is_ok() {
+        return false;
+    }
+    let parse = |year, month, day| {
+        let default = chrono::NaiveDate::from_ymd_opt(year, month, day)?.and_hms_opt(0, 0, 0)?;
+        dtparse::Parser::default()
+            .parse(
+                text,
+                None,
+                None,
+                false,
+                false,
+                Some(&default),
+                false,
+                &Default::default(),
+            )
+            .ok()
+            .map(|(date, offset, _)| (date, offset))
+    };
+    match (parse(2000, 1, 1), parse(2001, 2, 3)) {
+        (Some(one), Some(other)) => one != other,
+        // `into datetime` falls back to parsing it as human time, which is relative.
+        _ => true,
+    }
+}
+
+impl Command for AbsoluteIntoDatetime {
+    fn name(&self) -> &str {
+        "into datetime"
+    }
+
+    fn signature(&self) -> Signature {
+        nu_command::IntoDatetime.signature()
+    }
+
+    fn usage(&self) -> &str {
+        "Convert text or timestamp into a datetime; relative dates like \"now\" are refused."
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        // Its examples are formatted from the current time.
+        if call.has_flag(engine_state, stack, "list")? {
+            return Err(ShellError::GenericError {
+                error: "`into datetime --list` is disabled".into(),
+                msg: "its examples show the current time".into(),
+                span: Some(call.head),
+                help: None,
+                inner: vec![],
+            });
+        }
+        if call.has_flag(engine_state, stack, "format")? {
+            return nu_command::IntoDatetime.run(engine_state, stack, call, input);
+        }
+        let cell_paths: Vec<CellPath> = call.rest(engine_state, stack, 0)?;
+        let metadata = input.metadata();
+        let value = input.into_value(call.head)?;
+        if cell_paths.is_empty() {
+            Self::check(&value)?;
+        }
+        for path in &cell_paths {
+            if let Ok(target) = value.clone().follow_cell_path(&path.members, false) {
+                Self::check(&target)?;
+            }
+        }
+        nu_command::IntoDatetime.run(
+            engine_state,
+            stack,
+            call,
+            PipelineData::Value(value, metadata),
+        )
+    }
+}
+
+/// Wraps `open` so it refuses device and kernel files, like `/dev/urandom` or `/proc/uptime`,
+/// which read entropy or the state of the host. Unquoted glob patterns are refused too, as their
+/// matches aren't known until `open` expands them; so are glob characters in a path piped in,
+/// which `open` always expands.
+#[derive(Clone)]
+pub struct NoDeviceFiles {
+    inner: Box<dyn Command>,
+}
+
+/// Where device and kernel files live.
+const DEVICE_DIRS: &[&str] = &["/dev", "/proc", "/sys"];
+
+impl NoDeviceFiles {
+    pub fn new(inner: Box<dyn Command>) -> Self {
+        Self { inner }
+    }
+}
+
+impl Command for NoDeviceFiles {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn signature(&self) -> Signature {
+        self.inner.signature()
+    }
+
+    fn usage(&self) -> &str {
+        self.inner.usage()
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let cwd = engine_state.cwd(Some(stack))?.into_std_path_buf();
+        let mut paths = vec![];
+        for arg in call.rest::<Value>(engine_state, stack, 0)? {
+            let span = arg.span();
+            paths.push(match arg {
+                Value::Glob { val, no_expand, .. } => (val, !no_expand, span),
+                arg => (arg.coerce_into_string()?, false, span),
+            });
+        }
+        // Without a path argument, `open` reads one from the input.
+        if !call.has_positional_args(stack, 0) {
+            if let PipelineData::Value(value, _) = &input {
+                paths.push((value.coerce_string()?, true, value.span()));
+            }
+        }
+        for (path, glob, span) in paths {
+            let refused = |msg: &str| ShellError::GenericError {
+                error: format!("`{}` can't read device files", self.inner.name()),
+                msg: msg.into(),
+                span: Some(span),
+                help: None,
+                inner: vec![],
+            };
+            if glob && path.contains(['*', '?', '[']) {
+                return Err(refused("glob patterns are disabled; quote the path"));
+            }
+            let Ok(full) = cwd.join(&path).canonicalize() else {
+                continue;
+            };
+            let special = std::fs::metadata(&full).is_ok_and(|meta| !meta.file_type().is_file());
+            if special || DEVICE_DIRS.iter().any(|dir| full.starts_with(dir)) {
+                return Err(refused("this is a device or kernel file"));
+            }
+        }
+        self.inner.run(engine_state, stack, call, input)
+    }
+}
+
+/// Everything that shapes a sandboxed engine: which commands it has, which it loses, its limits
+/// and whether it sees the host's environment. The default is the full shell with no limits.
+#[derive(Debug, Clone, Default)]
+pub struct SandboxPolicy {
+    /// Command groups to build up from the language core; with none, the full shell is loaded.
+    pub profiles: Vec<Profile>,
+    /// Only these commands stay available, besides the language core.
+    pub allow: Option<Vec<String>>,
+    pub deny: Vec<String>,
+    pub read_only: bool,
+    pub deny_network: bool,
+    pub deny_filesystem: bool,
+    pub deterministic: bool,
+    /// Instant `date now` returns.
+    pub frozen_now: Option<DateTime<FixedOffset>>,
+    /// Directory `open`, `ls` and `save` are limited to.
+    pub root: Option<PathBuf>,
+    pub max_cpu: Option<Duration>,
+    /// Heap growth allowed, in bytes; needs [`CountingAllocator`](crate::memory::CountingAllocator).
+    pub max_memory: Option<usize>,
+    pub timeout: Option<Duration>,
+    /// Populate `$env` from the current process.
+    pub env: bool,
+}
+
+impl SandboxPolicy {
+    /// An [`EngineBuilder`] set up to build an engine following this policy.
+    pub fn builder(&self) -> EngineBuilder {
+        let mut builder = if self.profiles.is_empty() {
+            EngineBuilder::new().with_shell_commands()
+        } else {
+            self.profiles
+                .iter()
+                .fold(EngineBuilder::new(), |builder, profile| {
+                    builder.with_profile(*profile)
+                })
+        };
+        if self.env {
+            builder = builder.with_env();
+        }
+        if let Some(allow) = &self.allow {
+            builder = builder.allow(allow);
+        }
+        if !self.deny.is_empty() {
+            builder = builder.deny(&self.deny);
+        }
+        if let Some(root) = &self.root {
+            builder = builder.jail(root);
+        }
+        if let Some(limit) = self.max_cpu {
+            builder = builder.max_cpu(limit);
+        }
+        if let Some(limit) = self.max_memory {
+            builder = builder.max_memory(limit);
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if self.deterministic {
+            builder = builder.deterministic();
+        }
+        if let Some(now) = self.frozen_now {
+            builder = builder.freeze_time(now);
+        }
+        if self.read_only {
+            builder = builder.read_only();
+        }
+        if self.deny_network {
+            builder = builder.deny_network();
+        }
+        if self.deny_filesystem {
+            builder = builder.deny_filesystem();
+        }
+        builder
+    }
+}
+
+/// Why [`eval_sandboxed`] failed, sorted into what the caller can act on: a mistake in the code,
+/// something the policy takes away, a limit, or an ordinary failure while running.
+#[derive(Debug, Clone)]
+pub enum SandboxError {
+    /// The code doesn't parse.
+    ParseError { span: Span, msg: String },
+    /// The code calls a command the policy denies.
+    ForbiddenCommand { name: String, reason: String },
+    /// The code uses syntax the policy rejects, like redirecting into a file.
+    PolicyViolation { span: Span, msg: String },
+    /// Evaluation ran past the wall-clock timeout or the CPU budget.
+    Timeout,
+    /// Evaluation grew the heap past the memory limit.
+    MemoryLimitExceeded,
+    /// Evaluation failed for any other reason.
+    RuntimeError { shell_error: ShellError },
+    /// No engine could be built from the policy, e.g. because the jail root doesn't exist.
+    InvalidPolicy { msg: String },
+}
+
+impl std::fmt::Display for SandboxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ParseError { msg, .. } => write!(f, "Parse error: {msg}"),
+            Self::ForbiddenCommand { name, reason } => write!(f, "`{name}` is forbidden: {reason}"),
+            Self::PolicyViolation { msg, .. } => write!(f, "Not allowed by the policy: {msg}"),
+            Self::Timeout => f.write_str("Evaluation timed out"),
+            Self::MemoryLimitExceeded => f.write_str("Memory limit exceeded"),
+            Self::RuntimeError { shell_error } => write!(f, "{shell_error}"),
+            Self::InvalidPolicy { msg } => write!(f, "Invalid policy: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for SandboxError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::RuntimeError { shell_error } => Some(shell_error),
+            _ => None,
+        }
+    }
+}
+
+impl SandboxError {
+    /// Sort an error [`Engine::parse`] failed with. No code has run yet, so the engine's own
+    /// errors can be told apart by their text.
+    pub fn from_parse(shell_error: ShellError) -> Self {
+        let ShellError::GenericError {
+            error, msg, span, ..
+        } = &shell_error
+        else {
+            return Self::RuntimeError { shell_error };
+        };
+        let span = span.unwrap_or_else(Span::unknown);
+        let denied = error
+            .strip_prefix('`')
+            .and_then(|rest| rest.strip_suffix(DENIED_SUFFIX))
+            .and_then(|rest| rest.strip_suffix('`'));
+        match (error.as_str(), denied) {
+            (PARSE_ERROR, _) => Self::ParseError {
+                span,
+                msg: msg.clone(),
+            },
+            (POLICY_ERROR, _) => Self::PolicyViolation {
+                span,
+                msg: msg.clone(),
+            },
+            (_, Some(name)) => Self::ForbiddenCommand {
+                name: name.into(),
+                reason: msg.clone(),
+            },
+            _ => Self::RuntimeError { shell_error },
+        }
+    }
+
+    /// Sort an error evaluating on `engine` failed with. The evaluated code can raise errors with
+    /// any text, so a limit is only reported if `engine` ran into one; anything else is a
+    /// [`SandboxError::RuntimeError`].
+    pub fn from_eval(engine: &Engine, shell_error: ShellError) -> Self {
+        match engine.exceeded_limit() {
+            Some(Limit::Cpu | Limit::Timeout) => Self::Timeout,
+            Some(Limit::Memory) => Self::MemoryLimitExceeded,
+            None => Self::RuntimeError { shell_error },
+        }
+    }
+}
+
+/// Evaluate untrusted `code` with `input` in a fresh engine built from `policy`, and collect its
+/// output.
+pub fn eval_sandboxed(
+    code: &str,
+    input: Value,
+    policy: SandboxPolicy,
+) -> Result<Value, SandboxError> {
+    let mut engine = policy
+        .builder()
+        .build()
+        .map_err(|err| SandboxError::InvalidPolicy {
+            msg: err.to_string(),
+        })?;
+    engine.parse(code).map_err(SandboxError::from_parse)?;
+    engine
+        .eval_value(code, PipelineData::Value(input, None))
+        .map_err(|err| SandboxError::from_eval(&engine, err))
+}
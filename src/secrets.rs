@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command as Process;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use nu_engine::command_prelude::*;
+use nu_protocol::CustomValue;
+use serde::{Deserialize, Serialize};
+
+const MASK: &str = "********";
+
+/// Where `secret get` looks up secret values.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "provider", rename_all = "lowercase", deny_unknown_fields)]
+pub enum SecretProvider {
+    /// The host environment variable `{prefix}{name}`.
+    Env {
+        #[serde(default)]
+        prefix: String,
+    },
+    /// The contents of the file `{dir}/{name}`, with trailing whitespace trimmed.
+    File { dir: PathBuf },
+    /// The stdout of `{program} {name}`, with trailing whitespace trimmed. Names starting with
+    /// `-` are refused, so they can't be taken for an option.
+    #[serde(rename = "cmd")]
+    Command { program: String },
+    /// Key `{key}` of the KV v2 secret `{mount}/{path}` in HashiCorp Vault, for a name of the
+    /// form `path#key` (`key` defaults to `value`). The token is read from `VAULT_TOKEN`.
+    /// Paths are limited to `/`-separated segments of letters, digits, `-`, `_` and `.`.
+    #[cfg(feature = "vault")]
+    Vault {
+        addr: String,
+        #[serde(default = "default_vault_mount")]
+        mount: String,
+    },
+}
+
+#[cfg(feature = "vault")]
+fn default_vault_mount() -> String {
+    "secret".into()
+}
+
+impl SecretProvider {
+    /// Parse a provider spec: `env[:PREFIX]`, `file:DIR` or `cmd:PROGRAM`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        match spec.split_once(':').unwrap_or((spec, "")) {
+            ("env", prefix) => Ok(Self::Env {
+                prefix: prefix.into(),
+            }),
+            ("file", dir) if !dir.is_empty() => Ok(Self::File { dir: dir.into() }),
+            ("cmd", program) if !program.is_empty() => Ok(Self::Command {
+                program: program.into(),
+            }),
+            _ => Err(format!(
+                "Invalid secret provider '{spec}', expected env[:PREFIX], file:DIR or cmd:PROGRAM"
+            )),
+        }
+    }
+
+    pub fn fetch(&self, name: &str) -> Result<String, String> {
+        match self {
+            Self::Env { prefix } => {
+                std::env::var(format!("{prefix}{name}")).map_err(|err| err.to_string())
+            }
+            Self::File { dir } => std::fs::read_to_string(secret_file(dir, name)?)
+                .map(|secret| secret.trim_end().to_string())
+                .map_err(|err| err.to_string()),
+            Self::Command { program } => {
+                if name.starts_with('-') {
+                    return Err(format!("Invalid secret name '{name}'"));
+                }
+                let output = Process::new(program)
+                    .arg(name)
+                    .output()
+                    .map_err(|err| err.to_string())?;
+                if !output.status.success() {
+                    return Err(format!("{program} exited with {}", output.status));
+                }
+                String::from_utf8(output.stdout)
+                    .map(|secret| secret.trim_end().to_string())
+                    .map_err(|err| err.to_string())
+            }
+            #[cfg(feature = "vault")]
+            Self::Vault { addr, mount } => {
+                let (path, key) = vault_path(name)?;
+                let token = std::env::var("VAULT_TOKEN").map_err(|_| "VAULT_TOKEN is not set")?;
+                let response: serde_json::Value = ureq::get(&format!(
+                    "{}/v1/{mount}/data/{path}",
+                    addr.trim_end_matches('/')
+                ))
+                .set("X-Vault-Token", &token)
+                .call()
+                .map_err(|err| err.to_string())?
+                .into_json()
+                .map_err(|err| err.to_string())?;
+                response["data"]["data"][key]
+                    .as_str()
+                    .map(String::from)
+                    .ok_or_else(|| format!("No string key '{key}' in {mount}/{path}"))
+            }
+        }
+    }
+}
+
+/// The file holding secret `name` in `dir`, which the name may not lead out of: it has to be a
+/// plain file name, and the file, symlinks resolved, has to be in `dir`.
+fn secret_file(dir: &Path, name: &str) -> Result<PathBuf, String> {
+    let plain = matches!(
+        Path::new(name).components().collect::<Vec<_>>().as_slice(),
+        [Component::Normal(_)]
+    );
+    if !plain || name.contains(['/', '\\']) {
+        return Err(format!("Invalid secret name '{name}'"));
+    }
+    let dir = dir.canonicalize().map_err(|err| err.to_string())?;
+    let path = dir
+        .join(name)
+        .canonicalize()
+        .map_err(|err| err.to_string())?;
+    if !path.starts_with(&dir) {
+        return Err(format!("Secret '{name}' is outside {}", dir.display()));
+    }
+    Ok(path)
+}
+
+/// The path and key of the Vault secret `name`. The path has to be `/`-separated segments of
+/// letters, digits, `-`, `_` and `.`, none of them empty, `.` or `..`, so it can't leave the mount
+/// or be read as part of the query.
+#[cfg(feature = "vault")]
+fn vault_path(name: &str) -> Result<(&str, &str), String> {
+    let (path, key) = name.split_once('#').unwrap_or((name, "value"));
+    let valid = path.split('/').all(|segment| {
+        !matches!(segment, "" | "." | "..")
+            && segment
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    });
+    if !valid || key.is_empty() || key.contains('#') {
+        return Err(format!("Invalid secret name '{name}'"));
+    }
+    Ok((path, key))
+}
+
+/// A [`SecretProvider`] with an optional cache, so rotating credentials are refetched once
+/// `ttl` has passed rather than on every `secret get`.
+#[derive(Debug, Clone)]
+pub struct SecretStore {
+    provider: SecretProvider,
+    ttl: Option<Duration>,
+    cache: Arc<Mutex<HashMap<String, (Instant, String)>>>,
+}
+
+impl SecretStore {
+    pub fn new(provider: SecretProvider) -> Self {
+        Self {
+            provider,
+            ttl: None,
+            cache: Default::default(),
+        }
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Result<String, String> {
+        let Some(ttl) = self.ttl else {
+            return self.provider.fetch(name);
+        };
+
+        let mut cache = self.cache.lock().map_err(|err| err.to_string())?;
+        if let Some((fetched_at, secret)) = cache.get(name) {
+            if fetched_at.elapsed() < ttl {
+                return Ok(secret.clone());
+            }
+        }
+        let secret = self.provider.fetch(name)?;
+        cache.insert(name.into(), (Instant::now(), secret.clone()));
+        Ok(secret)
+    }
+}
+
+/// A secret string that renders as `********` in tables, output and debug formatting.
+///
+/// Nushell turns a custom value into a string through its base value, so string interpolation
+/// and `to text` get the mask as well, external commands refuse the value, and `http` leaves out
+/// a header set to it. [`SecretReveal`] is the only way to the real string, which from then on is
+/// a plain string like any other.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Secret {
+    secret: String,
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(MASK)
+    }
+}
+
+#[typetag::serde]
+impl CustomValue for Secret {
+    fn clone_value(&self, span: Span) -> Value {
+        Value::custom(Box::new(self.clone()), span)
+    }
+
+    fn type_name(&self) -> String {
+        "secret".into()
+    }
+
+    fn to_base_value(&self, span: Span) -> Result<Value, ShellError> {
+        Ok(Value::string(MASK, span))
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[derive(Clone)]
+pub struct SecretGet {
+    store: SecretStore,
+}
+
+impl SecretGet {
+    pub fn new(store: SecretStore) -> Self {
+        Self { store }
+    }
+}
+
+impl Command for SecretGet {
+    fn name(&self) -> &str {
+        "secret get"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("secret get")
+            .input_output_types(vec![(Type::Nothing, Type::Custom("secret".into()))])
+            .required("name", SyntaxShape::String, "Name of the secret.")
+            .category(Category::Misc)
+    }
+
+    fn usage(&self) -> &str {
+        "Fetch a host secret, masked even in string interpolation; `secret reveal` unwraps it."
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let name: Spanned<String> = call.req(engine_state, stack, 0)?;
+        let secret = self
+            .store
+            .get(&name.item)
+            .map_err(|msg| ShellError::GenericError {
+                error: format!("Secret '{}' is not available", name.item),
+                msg,
+                span: Some(name.span),
+                help: None,
+                inner: vec![],
+            })?;
+        Ok(Value::custom(Box::new(Secret { secret }), call.head).into_pipeline_data())
+    }
+}
+
+#[derive(Clone)]
+pub struct SecretReveal;
+
+impl Command for SecretReveal {
+    fn name(&self) -> &str {
+        "secret reveal"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("secret reveal")
+            .input_output_types(vec![(Type::Custom("secret".into()), Type::String)])
+            .category(Category::Misc)
+    }
+
+    fn usage(&self) -> &str {
+        "Unwrap a secret into a plain string, e.g. to pass it to an external command."
+    }
+
+    fn run(
+        &self,
+        _engine_state: &EngineState,
+        _stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let value = input.into_value(call.head)?;
+        let span = value.span();
+        match value.as_custom_value()?.as_any().downcast_ref::<Secret>() {
+            Some(secret) => {
+                Ok(Value::string(secret.secret.clone(), call.head).into_pipeline_data())
+            }
+            None => Err(ShellError::CantConvert {
+                to_type: "secret".into(),
+                from_type: value.get_type().to_string(),
+                span,
+                help: None,
+            }),
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::memory;
+
+/// How long each phase of [`EngineBuilder::build`] took, and how big the engine came out.
+///
+/// [`EngineBuilder::build`]: crate::EngineBuilder::build
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StartupReport {
+    pub phases: Vec<Phase>,
+    /// Commands registered, denied ones included.
+    pub decls: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Phase {
+    pub name: &'static str,
+    pub micros: u64,
+    /// Heap the phase left allocated, as counted by [`memory::CountingAllocator`]; zero if it
+    /// isn't installed.
+    pub bytes: i64,
+}
+
+impl StartupReport {
+    /// Run `phase`, recording it under `name`.
+    pub(crate) fn record<T>(&mut self, name: &'static str, phase: impl FnOnce() -> T) -> T {
+        let allocated = memory::allocated();
+        let started = Instant::now();
+        let result = phase();
+        self.phases.push(Phase {
+            name,
+            micros: started.elapsed().as_micros() as u64,
+            bytes: memory::allocated() as i64 - allocated as i64,
+        });
+        result
+    }
+
+    pub fn total_micros(&self) -> u64 {
+        self.phases.iter().map(|phase| phase.micros).sum()
+    }
+
+    pub fn total_bytes(&self) -> i64 {
+        self.phases.iter().map(|phase| phase.bytes).sum()
+    }
+}
@@ -0,0 +1,76 @@
+use nu_protocol::engine::Closure;
+use nu_protocol::{record, ShellError, Value};
+
+use crate::Engine;
+
+/// A named closure every result is also run through, on an engine of its own so that several
+/// can run side by side.
+pub struct Tee {
+    name: String,
+    closure: Closure,
+    engine: Engine,
+}
+
+impl Tee {
+    /// Parse `source` on `engine` and fork it to run the closure on.
+    pub fn new(engine: &mut Engine, name: &str, source: &str) -> Result<Self, ShellError> {
+        Ok(Self {
+            name: name.to_string(),
+            closure: engine.parse_closure(source)?,
+            engine: engine.fork(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Run the closure on `value`, turning a failure into an error value.
+    fn apply(&self, value: Value) -> Value {
+        let span = value.span();
+        self.engine
+            .eval_closure_with_value(&self.closure, value)
+            .and_then(|data| data.into_value(span))
+            .unwrap_or_else(|err| Value::error(err, span))
+    }
+}
+
+/// Run `result` through every tee at once, returning their outputs as `{tee, result}` records in
+/// the order the tees were given. A tee that fails or panics gets an error value for its result;
+/// the others still run.
+pub fn tee(tees: &[Tee], result: Value) -> Vec<Value> {
+    let span = result.span();
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = tees
+            .iter()
+            .map(|tee| {
+                let result = result.clone();
+                (tee, scope.spawn(move || tee.apply(result)))
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|(tee, handle)| {
+                let output = handle.join().unwrap_or_else(|_| {
+                    Value::error(
+                        ShellError::GenericError {
+                            error: format!("Tee '{}' panicked", tee.name),
+                            msg: String::new(),
+                            span: None,
+                            help: None,
+                            inner: vec![],
+                        },
+                        span,
+                    )
+                });
+                Value::record(
+                    record! {
+                        "tee" => Value::string(&tee.name, span),
+                        "result" => output,
+                    },
+                    span,
+                )
+            })
+            .collect()
+    })
+}
@@ -0,0 +1,63 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use nu_engine::command_prelude::*;
+use uaparser::{Parser, UserAgentParser};
+
+use crate::json::json_to_value;
+
+/// `ua parse <string>`: the browser, OS and device a user-agent string describes.
+#[derive(Clone)]
+pub struct UaParse {
+    parser: Arc<UserAgentParser>,
+}
+
+impl UaParse {
+    /// Load the uap-core `regexes.yaml` at `path`.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let parser = std::fs::File::open(path)
+            .map_err(|err| err.to_string())
+            .and_then(|file| UserAgentParser::from_file(file).map_err(|err| err.to_string()))
+            .map_err(|err| format!("Can't load user-agent regexes {}: {err}", path.display()))?;
+        Ok(Self {
+            parser: Arc::new(parser),
+        })
+    }
+}
+
+impl Command for UaParse {
+    fn name(&self) -> &str {
+        "ua parse"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("ua parse")
+            .input_output_types(vec![(Type::Nothing, Type::record())])
+            .required("ua", SyntaxShape::String, "User-agent string.")
+            .category(Category::Strings)
+    }
+
+    fn usage(&self) -> &str {
+        "Parse a user-agent string into its user_agent, os and device."
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let ua: String = call.req(engine_state, stack, 0)?;
+        let client = serde_json::to_value(self.parser.parse(&ua)).map_err(|err| {
+            ShellError::GenericError {
+                error: "Failed to convert the parsed user agent".into(),
+                msg: err.to_string(),
+                span: Some(call.head),
+                help: None,
+                inner: vec![],
+            }
+        })?;
+        Ok(json_to_value(client, call.head).into_pipeline_data())
+    }
+}
@@ -0,0 +1,469 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use nu_engine::command_prelude::*;
+use nu_engine::get_eval_block;
+use nu_protocol::ast;
+
+/// Storage behind the `open`, `save` and `ls` that [`Engine::use_vfs`] registers. Paths are
+/// `/`-separated and relative to the provider's root, whatever the script wrote: they have been
+/// through [`normalize`] before a provider sees them.
+///
+/// [`Engine::use_vfs`]: crate::Engine::use_vfs
+pub trait VfsProvider: Send + Sync {
+    fn read(&self, path: &str) -> io::Result<Vec<u8>>;
+
+    fn write(&self, path: &str, contents: &[u8], append: bool) -> io::Result<()>;
+
+    /// The entries directly under `dir`, `""` being the root.
+    fn list(&self, dir: &str) -> io::Result<Vec<VfsEntry>>;
+
+    fn exists(&self, path: &str) -> bool {
+        self.read(path).is_ok()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VfsEntry {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+}
+
+/// Resolve `path` against the root: leading `/` and `.` segments are dropped, and `..` or `~`
+/// are refused rather than resolved.
+pub fn normalize(path: &str) -> io::Result<String> {
+    if path.starts_with('~') {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "paths may not use `~`",
+        ));
+    }
+    let mut segments = vec![];
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    "paths may not use `..`",
+                ))
+            }
+            segment => segments.push(segment),
+        }
+    }
+    Ok(segments.join("/"))
+}
+
+/// Files held in memory, so scripts can read what the host put there and the host can read back
+/// what they saved. Directories exist as long as there are files in them.
+#[derive(Default)]
+pub struct MemoryFs {
+    files: Mutex<BTreeMap<String, Vec<u8>>>,
+}
+
+impl MemoryFs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&self, path: &str, contents: impl Into<Vec<u8>>) -> io::Result<()> {
+        self.write(&normalize(path)?, &contents.into(), false)
+    }
+
+    pub fn get(&self, path: &str) -> Option<Vec<u8>> {
+        self.read(&normalize(path).ok()?).ok()
+    }
+}
+
+impl VfsProvider for MemoryFs {
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        self.files
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .get(path)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such file"))
+    }
+
+    fn write(&self, path: &str, contents: &[u8], append: bool) -> io::Result<()> {
+        let mut files = self.files.lock().unwrap_or_else(|err| err.into_inner());
+        let file = files.entry(path.to_string()).or_default();
+        if !append {
+            file.clear();
+        }
+        file.extend_from_slice(contents);
+        Ok(())
+    }
+
+    fn list(&self, dir: &str) -> io::Result<Vec<VfsEntry>> {
+        let prefix = match dir {
+            "" => String::new(),
+            dir => format!("{dir}/"),
+        };
+        let files = self.files.lock().unwrap_or_else(|err| err.into_inner());
+        let mut entries: Vec<VfsEntry> = vec![];
+        for (path, contents) in files.range(prefix.clone()..) {
+            let Some(rest) = path.strip_prefix(&prefix) else {
+                break;
+            };
+            let entry = match rest.split_once('/') {
+                Some((subdir, _)) => VfsEntry {
+                    name: format!("{prefix}{subdir}"),
+                    is_dir: true,
+                    size: 0,
+                },
+                None => VfsEntry {
+                    name: path.clone(),
+                    is_dir: false,
+                    size: contents.len() as u64,
+                },
+            };
+            if entries.last() != Some(&entry) {
+                entries.push(entry);
+            }
+        }
+        if entries.is_empty() && !dir.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "no such directory"));
+        }
+        Ok(entries)
+    }
+}
+
+/// A directory on disk standing in for the whole filesystem. As with
+/// [`Jailed`](crate::jail::Jailed), the longest existing part of each path is canonicalized, so a
+/// symlink can't lead out of it.
+pub struct DirFs {
+    root: PathBuf,
+}
+
+impl DirFs {
+    pub fn new(root: impl Into<PathBuf>) -> io::Result<Self> {
+        Ok(Self {
+            root: root.into().canonicalize()?,
+        })
+    }
+
+    fn resolve(&self, path: &str) -> io::Result<PathBuf> {
+        let full = self.root.join(path);
+        let existing = full
+            .ancestors()
+            .find_map(|ancestor| ancestor.canonicalize().ok())
+            .unwrap_or_default();
+        if !existing.starts_with(&self.root) {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "this path is outside of the root",
+            ));
+        }
+        Ok(full)
+    }
+}
+
+impl VfsProvider for DirFs {
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        std::fs::read(self.resolve(path)?)
+    }
+
+    fn write(&self, path: &str, contents: &[u8], append: bool) -> io::Result<()> {
+        std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(self.resolve(path)?)?
+            .write_all(contents)
+    }
+
+    fn list(&self, dir: &str) -> io::Result<Vec<VfsEntry>> {
+        let mut entries = vec![];
+        for entry in std::fs::read_dir(self.resolve(dir)?)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            entries.push(VfsEntry {
+                name: if dir.is_empty() {
+                    name
+                } else {
+                    format!("{dir}/{name}")
+                },
+                is_dir: metadata.is_dir(),
+                size: metadata.len(),
+            });
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+}
+
+/// Another provider with writes refused.
+pub struct ReadOnly<P>(pub P);
+
+impl<P: VfsProvider> VfsProvider for ReadOnly<P> {
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        self.0.read(path)
+    }
+
+    fn write(&self, _path: &str, _contents: &[u8], _append: bool) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "the filesystem is read-only",
+        ))
+    }
+
+    fn list(&self, dir: &str) -> io::Result<Vec<VfsEntry>> {
+        self.0.list(dir)
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.0.exists(path)
+    }
+}
+
+fn vfs_error(action: &str, path: &Spanned<String>, err: io::Error) -> ShellError {
+    ShellError::GenericError {
+        error: format!("Can't {action} {}", path.item),
+        msg: err.to_string(),
+        span: Some(path.span),
+        help: None,
+        inner: vec![],
+    }
+}
+
+/// Run the `from <ext>` or `to <ext>` command for `path`'s extension on `input`, if there is one.
+fn convert(
+    engine_state: &EngineState,
+    stack: &mut Stack,
+    direction: &str,
+    path: &Spanned<String>,
+    input: PipelineData,
+) -> Result<Option<PipelineData>, ShellError> {
+    let Some((_, ext)) = path.item.rsplit_once('.') else {
+        return Ok(None);
+    };
+    let name = format!("{direction} {}", ext.to_lowercase());
+    let Some(decl_id) = engine_state.find_decl(name.as_bytes(), &[]) else {
+        return Ok(None);
+    };
+    let decl = engine_state.get_decl(decl_id);
+    let output = match decl.block_id() {
+        Some(block_id) => {
+            let block = engine_state.get_block(block_id);
+            get_eval_block(engine_state)(engine_state, stack, block, input)
+        }
+        None => {
+            let call = ast::Call::new(path.span);
+            decl.run(engine_state, stack, &(&call).into(), input)
+        }
+    };
+    output.map(Some).map_err(|inner| ShellError::GenericError {
+        error: format!("Error while converting {} with `{name}`", path.item),
+        msg: inner.to_string(),
+        span: Some(path.span),
+        help: None,
+        inner: vec![inner],
+    })
+}
+
+/// `open <path>`, reading through a [`VfsProvider`]. Like the real `open`, the contents are
+/// parsed with the `from` command for the file's extension unless `--raw` is given.
+#[derive(Clone)]
+pub struct VfsOpen {
+    vfs: Arc<dyn VfsProvider>,
+}
+
+impl VfsOpen {
+    pub fn new(vfs: Arc<dyn VfsProvider>) -> Self {
+        Self { vfs }
+    }
+}
+
+impl Command for VfsOpen {
+    fn name(&self) -> &str {
+        "open"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("open")
+            .input_output_types(vec![(Type::Nothing, Type::Any)])
+            .required("path", SyntaxShape::String, "The file to open.")
+            .switch("raw", "open file as raw text or binary", Some('r'))
+            .category(Category::FileSystem)
+    }
+
+    fn usage(&self) -> &str {
+        "Load a file from the host-provided filesystem."
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let path: Spanned<String> = call.req(engine_state, stack, 0)?;
+        let raw = call.has_flag(engine_state, stack, "raw")?;
+        let contents = normalize(&path.item)
+            .and_then(|normalized| self.vfs.read(&normalized))
+            .map_err(|err| vfs_error("open", &path, err))?;
+        let value = match String::from_utf8(contents) {
+            Ok(text) => Value::string(text, call.head),
+            Err(err) => Value::binary(err.into_bytes(), call.head),
+        };
+        if raw {
+            return Ok(value.into_pipeline_data());
+        }
+        let converted = convert(
+            engine_state,
+            stack,
+            "from",
+            &path,
+            value.clone().into_pipeline_data(),
+        )?;
+        Ok(converted.unwrap_or_else(|| value.into_pipeline_data()))
+    }
+}
+
+/// `save <path>`, writing through a [`VfsProvider`]. Values other than strings and binary are
+/// serialized with the `to` command for the file's extension.
+#[derive(Clone)]
+pub struct VfsSave {
+    vfs: Arc<dyn VfsProvider>,
+}
+
+impl VfsSave {
+    pub fn new(vfs: Arc<dyn VfsProvider>) -> Self {
+        Self { vfs }
+    }
+}
+
+impl Command for VfsSave {
+    fn name(&self) -> &str {
+        "save"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("save")
+            .input_output_types(vec![(Type::Any, Type::Nothing)])
+            .required("path", SyntaxShape::String, "The file to save to.")
+            .switch("raw", "save the input as is, without `to`", Some('r'))
+            .switch("append", "append input to the end of the file", Some('a'))
+            .switch("force", "overwrite the destination", Some('f'))
+            .category(Category::FileSystem)
+    }
+
+    fn usage(&self) -> &str {
+        "Save the input to a file in the host-provided filesystem."
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let path: Spanned<String> = call.req(engine_state, stack, 0)?;
+        let raw = call.has_flag(engine_state, stack, "raw")?;
+        let append = call.has_flag(engine_state, stack, "append")?;
+        let force = call.has_flag(engine_state, stack, "force")?;
+        let normalized = normalize(&path.item).map_err(|err| vfs_error("save", &path, err))?;
+        if !append && !force && self.vfs.exists(&normalized) {
+            return Err(vfs_error(
+                "save",
+                &path,
+                io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    "file already exists, use --force to overwrite",
+                ),
+            ));
+        }
+
+        let mut value = input.into_value(call.head)?;
+        if !raw && !matches!(value, Value::String { .. } | Value::Binary { .. }) {
+            let data = value.clone().into_pipeline_data();
+            if let Some(converted) = convert(engine_state, stack, "to", &path, data)? {
+                value = converted.into_value(call.head)?;
+            }
+        }
+        let contents = match value {
+            Value::String { val, .. } => val.into_bytes(),
+            Value::Binary { val, .. } => val,
+            other => {
+                return Err(ShellError::GenericError {
+                    error: format!("Can't save {}", path.item),
+                    msg: format!("no `to` command turns a {} into text", other.get_type()),
+                    span: Some(path.span),
+                    help: Some("save it as .json, .nuon, .csv or another known format".into()),
+                    inner: vec![],
+                })
+            }
+        };
+        self.vfs
+            .write(&normalized, &contents, append)
+            .map_err(|err| vfs_error("save", &path, err))?;
+        Ok(PipelineData::empty())
+    }
+}
+
+/// `ls [dir]`, listing a directory of a [`VfsProvider`].
+#[derive(Clone)]
+pub struct VfsLs {
+    vfs: Arc<dyn VfsProvider>,
+}
+
+impl VfsLs {
+    pub fn new(vfs: Arc<dyn VfsProvider>) -> Self {
+        Self { vfs }
+    }
+}
+
+impl Command for VfsLs {
+    fn name(&self) -> &str {
+        "ls"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("ls")
+            .input_output_types(vec![(Type::Nothing, Type::table())])
+            .optional("path", SyntaxShape::String, "The directory to list.")
+            .category(Category::FileSystem)
+    }
+
+    fn usage(&self) -> &str {
+        "List the files in a directory of the host-provided filesystem."
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let head = call.head;
+        let dir = call
+            .opt::<Spanned<String>>(engine_state, stack, 0)?
+            .unwrap_or_else(|| String::new().into_spanned(head));
+        let entries = normalize(&dir.item)
+            .and_then(|normalized| self.vfs.list(&normalized))
+            .map_err(|err| vfs_error("list", &dir, err))?;
+        let rows = entries
+            .into_iter()
+            .map(|entry| {
+                Value::record(
+                    record! {
+                        "name" => Value::string(entry.name, head),
+                        "type" => Value::string(if entry.is_dir { "dir" } else { "file" }, head),
+                        "size" => Value::filesize(entry.size as i64, head),
+                    },
+                    head,
+                )
+            })
+            .collect();
+        Ok(Value::list(rows, head).into_pipeline_data())
+    }
+}
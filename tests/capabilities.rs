@@ -0,0 +1,55 @@
+use mini_nu::EngineBuilder;
+
+#[test]
+fn every_toggle_leaves_no_side_effects() {
+    let engine = EngineBuilder::new()
+        .with_shell_commands()
+        .deterministic()
+        .read_only()
+        .deny_network()
+        .deny_filesystem()
+        .build()
+        .expect("engine builds");
+    let capabilities = engine.capabilities();
+
+    assert_eq!(capabilities.filesystem_read, Vec::<&str>::new());
+    assert_eq!(capabilities.filesystem_write, Vec::<&str>::new());
+    assert_eq!(capabilities.network, Vec::<&str>::new());
+    assert_eq!(capabilities.process_spawn, Vec::<&str>::new());
+    assert!(!capabilities.host_env);
+}
+
+#[test]
+fn full_shell_reports_what_it_exposes() {
+    let engine = EngineBuilder::new()
+        .with_shell_commands()
+        .build()
+        .expect("engine builds");
+    let capabilities = engine.capabilities();
+
+    assert!(capabilities.filesystem_read.contains(&"open"));
+    assert!(capabilities.filesystem_write.contains(&"save"));
+    assert!(capabilities.network.contains(&"http get"));
+    assert!(capabilities.process_spawn.contains(&"kill"));
+    assert!(capabilities.process_spawn.contains(&"run-external"));
+    assert!(!capabilities.host_env);
+}
+
+#[test]
+fn host_env_is_reported_when_gathered() {
+    let gathered = EngineBuilder::new()
+        .with_shell_commands()
+        .with_env()
+        .build()
+        .expect("engine builds");
+    assert!(gathered.capabilities().host_env);
+    assert!(gathered.fork().capabilities().host_env);
+
+    let deterministic = EngineBuilder::new()
+        .with_shell_commands()
+        .with_env()
+        .deterministic()
+        .build()
+        .expect("engine builds");
+    assert!(!deterministic.capabilities().host_env);
+}
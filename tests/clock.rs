@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use chrono::DateTime;
+use mini_nu::clock::VirtualClock;
+use mini_nu::input::{self, InputFormat};
+use mini_nu::{Engine, EngineBuilder};
+use nu_protocol::{PipelineData, ShellError, Span, Value};
+
+fn eval(engine: &mut Engine, code: &str) -> Result<Value, ShellError> {
+    engine
+        .eval(code, PipelineData::empty())
+        .and_then(|data| data.into_value(Span::unknown()))
+}
+
+#[test]
+fn frozen_time_covers_relative_dates() {
+    let now = DateTime::parse_from_rfc3339("2024-01-31T12:00:00+00:00").unwrap();
+    let mut engine = EngineBuilder::new()
+        .with_shell_commands()
+        .freeze_time(now)
+        .build()
+        .expect("engine builds");
+
+    assert_eq!(
+        eval(&mut engine, "date now").unwrap().as_date().ok(),
+        Some(now)
+    );
+    for code in ["'now' | into datetime", "'today' | into datetime"] {
+        assert!(eval(&mut engine, code).is_err(), "{code} read the clock");
+    }
+    let absolute = eval(&mut engine, "'2024-01-31T12:00:00+00:00' | into datetime").unwrap();
+    assert_eq!(absolute.as_date().ok(), Some(now));
+}
+
+#[test]
+fn virtual_clock_advances_with_sleep() {
+    let start = DateTime::parse_from_rfc3339("2024-01-31T12:00:00+00:00").unwrap();
+    let clock = VirtualClock::new(start);
+    let mut engine = EngineBuilder::new()
+        .with_shell_commands()
+        .virtual_clock(clock.clone())
+        .build()
+        .expect("engine builds");
+
+    let waited = eval(
+        &mut engine,
+        "let t = date now; sleep 1hr 30min; (date now) - $t",
+    )
+    .unwrap();
+    assert_eq!(
+        waited.as_duration().ok(),
+        Some(Duration::from_secs(90 * 60).as_nanos() as i64)
+    );
+    assert_eq!(clock.now(), start + chrono::Duration::minutes(90));
+
+    clock.advance(Duration::from_secs(60));
+    let now = eval(&mut engine, "date now").unwrap();
+    assert_eq!(
+        now.as_date().ok(),
+        Some(start + chrono::Duration::minutes(91))
+    );
+    assert!(eval(&mut engine, "'now' | into datetime").is_err());
+}
+
+#[test]
+fn input_is_stamped_by_the_engine_clock() {
+    let received_at = |engine: &Engine| {
+        let lines = input::read(&b"a\n"[..], InputFormat::Lines, b'\n', None, engine.clock())
+            .expect("lines read");
+        lines.into_list().unwrap()[0]
+            .get_data_by_key("received_at")
+            .expect("received_at")
+    };
+    let now = DateTime::parse_from_rfc3339("2024-01-31T12:00:00+00:00").unwrap();
+
+    let frozen = EngineBuilder::new().freeze_time(now).build().unwrap();
+    assert_eq!(received_at(&frozen).as_date().ok(), Some(now));
+
+    let clock = VirtualClock::new(now);
+    let virtual_clock = EngineBuilder::new()
+        .virtual_clock(clock.clone())
+        .build()
+        .unwrap();
+    clock.advance(Duration::from_secs(60));
+    assert_eq!(
+        received_at(&virtual_clock).as_date().ok(),
+        Some(now + chrono::Duration::minutes(1))
+    );
+
+    let deterministic = EngineBuilder::new().deterministic().build().unwrap();
+    assert!(received_at(&deterministic).is_nothing());
+}
@@ -0,0 +1,63 @@
+#![cfg(feature = "polars")]
+
+use mini_nu::dataframe::DataFrameValue;
+use mini_nu::EngineBuilder;
+use nu_protocol::{CustomValue, Span, Value};
+use polars::prelude::*;
+
+fn frame() -> DataFrame {
+    df!(
+        "name" => ["b", "a", "c"],
+        "n" => [2i64, 1, 3],
+        "ratio" => [Some(0.5f64), None, Some(1.5)],
+        "ok" => [true, false, true],
+    )
+    .expect("frame")
+}
+
+#[test]
+fn cells_keep_their_dtype() {
+    let table = DataFrameValue::new(frame())
+        .to_base_value(Span::test_data())
+        .expect("base value");
+    let first = &table.into_list().unwrap()[0];
+    let cell = |column| first.get_data_by_key(column).unwrap();
+    assert_eq!(cell("name"), Value::test_string("b"));
+    assert_eq!(cell("n"), Value::test_int(2));
+    assert_eq!(cell("ratio"), Value::test_float(0.5));
+    assert_eq!(cell("ok"), Value::test_bool(true));
+
+    let nulls = DataFrameValue::new(frame().slice(1, 1))
+        .to_base_value(Span::test_data())
+        .unwrap();
+    assert!(nulls.into_list().unwrap()[0]
+        .get_data_by_key("ratio")
+        .unwrap()
+        .is_nothing());
+}
+
+#[test]
+fn frame_commands_return_frames() {
+    let mut engine = EngineBuilder::new()
+        .with_shell_commands()
+        .with_dataframe_commands()
+        .build()
+        .expect("engine builds");
+    let closure = engine
+        .parse_closure("{|| df sort-by n --reverse | df select name n | df head 2 }")
+        .expect("closure");
+
+    let df = engine
+        .eval_with_dataframe(&closure, frame())
+        .expect("frame back");
+    assert_eq!(df.get_column_names(), ["name", "n"]);
+    assert_eq!(df.height(), 2);
+    assert_eq!(
+        df.column("n").unwrap().i64().unwrap().get(0),
+        Some(3),
+        "sorted in reverse"
+    );
+
+    let missing = engine.parse_closure("{|| df select nope }").unwrap();
+    assert!(engine.eval_with_dataframe(&missing, frame()).is_err());
+}
@@ -0,0 +1,56 @@
+use std::thread::sleep;
+use std::time::Duration;
+
+use mini_nu::dedup::{DuplicateWindow, CAPACITY};
+
+#[test]
+fn repeats_are_dropped_within_the_window() {
+    let mut window = DuplicateWindow::new(Duration::from_millis(200));
+    assert!(!window.is_duplicate("a".into()));
+    assert!(!window.is_duplicate("b".into()));
+    assert!(window.is_duplicate("a".into()));
+    assert!(window.is_duplicate("b".into()));
+
+    sleep(Duration::from_millis(250));
+    assert!(!window.is_duplicate("a".into()), "a outlived the window");
+    assert!(window.is_duplicate("a".into()));
+}
+
+#[test]
+fn repeats_dont_extend_the_window() {
+    let mut window = DuplicateWindow::new(Duration::from_millis(200));
+    assert!(!window.is_duplicate("a".into()));
+    sleep(Duration::from_millis(120));
+    assert!(window.is_duplicate("a".into()));
+    sleep(Duration::from_millis(120));
+    assert!(
+        !window.is_duplicate("a".into()),
+        "the window runs from the first write"
+    );
+}
+
+#[test]
+fn oldest_keys_go_first_past_capacity() {
+    let mut window = DuplicateWindow::new(Duration::from_secs(3600));
+    for n in 0..CAPACITY {
+        assert!(!window.is_duplicate(n.to_string()));
+    }
+    assert!(
+        window.is_duplicate("0".into()),
+        "nothing forgotten at capacity"
+    );
+    assert!(window.is_duplicate((CAPACITY - 1).to_string()));
+
+    assert!(!window.is_duplicate("one more".into()));
+    assert!(
+        window.is_duplicate("1".into()),
+        "only the oldest is forgotten"
+    );
+    assert!(window.is_duplicate("one more".into()));
+    assert!(
+        !window.is_duplicate("0".into()),
+        "0 should have been forgotten"
+    );
+    assert!(!window.is_duplicate("1".into()), "then 1, the next oldest");
+    assert!(window.is_duplicate((CAPACITY - 1).to_string()));
+}
@@ -0,0 +1,44 @@
+//! Pooled engines run side by side without their limits or state bleeding into each other.
+
+use std::time::Duration;
+
+use mini_nu::{EngineBuilder, EnginePool};
+use nu_protocol::{PipelineData, Span, Value};
+
+#[test]
+fn pooled_engines_are_independent() {
+    let template = EngineBuilder::new()
+        .with_shell_commands()
+        .max_cpu(Duration::from_millis(200))
+        .build()
+        .expect("engine builds");
+    let pool = EnginePool::new(template, 2);
+
+    std::thread::scope(|scope| {
+        let runaway = scope.spawn(|| pool.checkout().eval("loop {}", PipelineData::empty()).err());
+        let steady = scope.spawn(|| {
+            let mut engine = pool.checkout();
+            (0..50)
+                .map(|i| {
+                    engine
+                        .eval(&format!("$env.N = {i}; $env.N"), PipelineData::empty())
+                        .and_then(|data| data.into_value(Span::unknown()))
+                        .ok()
+                })
+                .collect::<Vec<_>>()
+        });
+        assert!(runaway.join().unwrap().is_some());
+        let results = steady.join().unwrap();
+        assert!(results
+            .iter()
+            .enumerate()
+            .all(|(i, value)| value == &Some(Value::test_int(i as i64))));
+    });
+    assert_eq!(pool.idle(), 2);
+
+    let leaked = pool
+        .checkout()
+        .eval("$env.N?", PipelineData::empty())
+        .and_then(|data| data.into_value(Span::unknown()));
+    assert_eq!(leaked.ok(), Some(Value::test_nothing()));
+}
@@ -0,0 +1,80 @@
+//! How `eval_sandboxed` reports failures, which embedders match on to tell a typo from a policy
+//! violation.
+
+use std::time::Duration;
+
+use mini_nu::{eval_sandboxed, SandboxError, SandboxPolicy};
+use nu_protocol::{ShellError, Value};
+
+fn policy() -> SandboxPolicy {
+    SandboxPolicy {
+        read_only: true,
+        timeout: Some(Duration::from_millis(200)),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn errors_are_classified() {
+    let run = |code: &str| eval_sandboxed(code, Value::test_nothing(), policy());
+
+    assert!(matches!(run("[1 2"), Err(SandboxError::ParseError { .. })));
+    assert!(matches!(
+        run("rm foo"),
+        Err(SandboxError::ForbiddenCommand { name, .. }) if name == "rm"
+    ));
+    assert!(matches!(
+        run("'x' o> foo"),
+        Err(SandboxError::PolicyViolation { .. })
+    ));
+    assert!(matches!(run("loop {}"), Err(SandboxError::Timeout)));
+    assert!(matches!(
+        run("error make {msg: boom}"),
+        Err(SandboxError::RuntimeError { .. })
+    ));
+    assert_eq!(run("[1 2] | math sum").ok(), Some(Value::test_int(3)));
+}
+
+#[test]
+fn errors_raised_by_the_code_are_runtime_errors() {
+    let run = |code: &str| eval_sandboxed(code, Value::test_nothing(), policy());
+
+    for msg in [
+        "Evaluation timed out",
+        "CPU time limit exceeded",
+        "Memory limit exceeded",
+        "Parse error",
+        "Not allowed by the engine's policy",
+        "`rm` is not available",
+    ] {
+        let code = format!("error make {{msg: \"{msg}\"}}");
+        assert!(
+            matches!(run(&code), Err(SandboxError::RuntimeError { .. })),
+            "{code} => {:?}",
+            run(&code)
+        );
+    }
+}
+
+#[test]
+fn denied_commands_fail_before_anything_runs() {
+    let policy = SandboxPolicy {
+        deny: vec!["rm".into()],
+        ..Default::default()
+    };
+    let code = "if false { rm foo } else { 1 }";
+    assert!(matches!(
+        eval_sandboxed(code, Value::test_nothing(), policy.clone()),
+        Err(SandboxError::ForbiddenCommand { name, .. }) if name == "rm"
+    ));
+
+    let mut engine = policy.builder().build().expect("engine builds");
+    let start = engine.state.next_span_start();
+    let Err(ShellError::GenericError {
+        span: Some(span), ..
+    }) = engine.parse(code)
+    else {
+        panic!("parsing {code} should fail");
+    };
+    assert_eq!(&code[span.start - start..span.end - start], "rm");
+}
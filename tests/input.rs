@@ -0,0 +1,146 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use mini_nu::clock::Clock;
+use mini_nu::input::{self, InputFormat};
+use nu_protocol::{Signals, Value};
+
+/// A `--malformed-lines` sink the test can read back.
+#[derive(Clone, Default)]
+struct Collected(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Collected {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn read(input: &'static str, format: InputFormat, delimiter: u8) -> Result<Vec<Value>, String> {
+    input::read(input.as_bytes(), format, delimiter, None, Clock::Denied)
+        .map(|value| value.into_list().unwrap())
+}
+
+#[test]
+fn json_is_one_document() {
+    let value = input::read(
+        "{\"a\": [1, 2.5, null]}".as_bytes(),
+        InputFormat::Json,
+        b'\n',
+        None,
+        Clock::Denied,
+    )
+    .unwrap();
+    let a = value.get_data_by_key("a").unwrap().into_list().unwrap();
+    assert_eq!(
+        a,
+        [
+            Value::test_int(1),
+            Value::test_float(2.5),
+            Value::test_nothing()
+        ]
+    );
+}
+
+#[test]
+fn lines_are_numbered() {
+    let vals = read("one\r\ntwo\n", InputFormat::Lines, b'\n').unwrap();
+    let lines: Vec<_> = vals
+        .iter()
+        .map(|v| (v.get_data_by_key("line"), v.get_data_by_key("n")))
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            (Some(Value::test_string("one")), Some(Value::test_int(1))),
+            (Some(Value::test_string("two")), Some(Value::test_int(2))),
+        ]
+    );
+}
+
+#[test]
+fn ndjson_skips_blank_lines_and_reports_bad_ones() {
+    let vals = read("{\"n\": 1}\n\n{\"n\": 2}\n", InputFormat::Ndjson, b'\n').unwrap();
+    assert_eq!(vals.len(), 2);
+    assert_eq!(vals[1].get_data_by_key("n"), Some(Value::test_int(2)));
+
+    let err = read("{\"n\": 1}\nnope\n", InputFormat::Ndjson, b'\n').unwrap_err();
+    assert!(err.contains("line 2"), "{err}");
+
+    let malformed = Collected::default();
+    let vals = input::read(
+        "{\"n\": 1}\nnope\n{\"n\": 3}".as_bytes(),
+        InputFormat::Ndjson,
+        b'\n',
+        Some(Box::new(malformed.clone())),
+        Clock::Denied,
+    )
+    .unwrap();
+    assert_eq!(vals.into_list().unwrap().len(), 2);
+    let report: serde_json::Value =
+        serde_json::from_slice(&malformed.0.lock().unwrap()).expect("one JSON report");
+    assert_eq!(report["n"], 2);
+    assert_eq!(report["line"], "nope");
+}
+
+#[test]
+fn csv_and_tsv_read_as_tables_of_strings() {
+    let csv = read("a,b\n1,x\n2,y\n", InputFormat::Csv, b'\n').unwrap();
+    let tsv = read("a\tb\n1\tx\n2\ty\n", InputFormat::Tsv, b'\n').unwrap();
+    assert_eq!(csv, tsv);
+    assert_eq!(csv[0].get_data_by_key("a"), Some(Value::test_string("1")));
+    assert_eq!(csv[1].get_data_by_key("b"), Some(Value::test_string("y")));
+}
+
+#[test]
+fn lines_split_on_nul() {
+    let vals = read("a b\0c\r\n", InputFormat::Lines, b'\0').unwrap();
+    assert_eq!(
+        vals[0].get_data_by_key("line"),
+        Some(Value::test_string("a b"))
+    );
+    assert_eq!(
+        vals[1].get_data_by_key("line"),
+        Some(Value::test_string("c\r\n"))
+    );
+}
+
+#[test]
+fn json_cant_stream() {
+    let streamed = input::stream(
+        "{}".as_bytes(),
+        InputFormat::Json,
+        b'\n',
+        None,
+        Clock::Denied,
+        Signals::empty(),
+    );
+    assert!(streamed.is_err());
+}
+
+#[test]
+fn streams_turn_bad_input_into_error_values() {
+    let stream = input::stream(
+        "{\"n\": 1}\nnope\n{\"n\": 3}\n".as_bytes(),
+        InputFormat::Ndjson,
+        b'\n',
+        None,
+        Clock::Denied,
+        Signals::empty(),
+    )
+    .unwrap();
+    let vals: Vec<_> = stream.into_iter().collect();
+    assert_eq!(vals.len(), 3);
+    assert!(matches!(vals[1], Value::Error { .. }));
+    assert_eq!(vals[2].get_data_by_key("n"), Some(Value::test_int(3)));
+}
+
+#[test]
+fn bytes_come_in_chunks() {
+    let vals = read("abcdefghij", InputFormat::Bytes { chunk_size: 4 }, b'\n').unwrap();
+    let chunks: Vec<_> = vals.into_iter().map(|v| v.into_binary().unwrap()).collect();
+    assert_eq!(chunks, [b"abcd".to_vec(), b"efgh".to_vec(), b"ij".to_vec()]);
+}
@@ -0,0 +1,43 @@
+use chrono::DateTime;
+use mini_nu::json::{json_to_value, value_to_json};
+use nu_protocol::{record, Span, Value};
+use serde_json::json;
+
+#[test]
+fn json_round_trips() {
+    let json = json!({"a": [1, -2, 2.5, null, true, "s"], "b": {"c": {}}});
+    let value = json_to_value(json.clone(), Span::test_data());
+    assert_eq!(
+        value.get_data_by_key("a").unwrap().into_list().unwrap()[2],
+        Value::test_float(2.5)
+    );
+    assert_eq!(value_to_json(&value), json);
+}
+
+#[test]
+fn numbers_past_i64_become_floats() {
+    let value = json_to_value(json!(u64::MAX), Span::test_data());
+    assert_eq!(value, Value::test_float(u64::MAX as f64));
+}
+
+#[test]
+fn values_without_a_json_type_are_reduced() {
+    let date = DateTime::parse_from_rfc3339("2024-01-31T12:00:00+01:00").unwrap();
+    let value = Value::test_record(record! {
+        "size" => Value::test_filesize(1024),
+        "took" => Value::test_duration(1_500),
+        "at" => Value::test_date(date),
+        "bytes" => Value::test_binary(vec![1, 2]),
+        "nan" => Value::test_float(f64::NAN),
+    });
+    assert_eq!(
+        value_to_json(&value),
+        json!({
+            "size": 1024,
+            "took": 1500,
+            "at": "2024-01-31T12:00:00+01:00",
+            "bytes": [1, 2],
+            "nan": null,
+        })
+    );
+}
@@ -0,0 +1,87 @@
+//! Limits stay in force while a result streams, closures applied to it included.
+
+use std::time::{Duration, Instant};
+
+use mini_nu::input::{self, InputFormat};
+use mini_nu::{EngineBuilder, Limit, SandboxError};
+use nu_protocol::{PipelineData, Value};
+
+#[test]
+fn closures_share_the_stream_timeout() {
+    let mut engine = EngineBuilder::new()
+        .with_shell_commands()
+        .timeout(Duration::from_millis(200))
+        .build()
+        .expect("engine builds");
+    let closure = engine.parse_closure("{|x| $x }").expect("closure");
+
+    let started = Instant::now();
+    let stream = engine
+        .eval("1.. | each {|i| sleep 1ms; $i }", PipelineData::empty())
+        .expect("stream starts");
+    for value in stream.into_iter() {
+        let _ = engine.eval_closure_with_value(&closure, value);
+        assert!(
+            started.elapsed() < Duration::from_secs(5),
+            "closures kept restarting the timeout"
+        );
+    }
+    assert!(engine.limit_exceeded().is_some());
+
+    engine.rearm_limits().expect("rearm");
+    assert!(engine.limit_exceeded().is_none());
+}
+
+#[test]
+fn cpu_budget_counts_cpu_time_only() {
+    let mut engine = EngineBuilder::new()
+        .with_shell_commands()
+        .max_cpu(Duration::from_millis(100))
+        .build()
+        .expect("engine builds");
+
+    let slept = engine.eval_value("sleep 300ms; 1", PipelineData::empty());
+    assert_eq!(slept.ok(), Some(Value::test_int(1)));
+
+    let looped = engine.eval("loop {}", PipelineData::empty());
+    assert!(matches!(
+        looped
+            .map(|_| ())
+            .map_err(|err| SandboxError::from_eval(&engine, err)),
+        Err(SandboxError::Timeout)
+    ));
+    assert_eq!(engine.exceeded_limit(), Some(Limit::Cpu));
+
+    let stream = engine
+        .eval("1.. | each {|i| $i }", PipelineData::empty())
+        .expect("stream starts");
+    let drained = stream.into_iter().count();
+    assert!(drained > 0);
+    assert!(engine.limit_exceeded().is_some());
+}
+
+#[test]
+fn endless_input_stops_at_the_timeout() {
+    let mut engine = EngineBuilder::new()
+        .with_shell_commands()
+        .timeout(Duration::from_millis(200))
+        .build()
+        .expect("engine builds");
+    let lines = input::stream(
+        std::io::repeat(b'\n'),
+        InputFormat::Lines,
+        b'\n',
+        None,
+        engine.clock(),
+        engine.state.signals().clone(),
+    )
+    .expect("stream");
+
+    let started = Instant::now();
+    let stream = engine
+        .eval("each {|l| $l.n }", PipelineData::ListStream(lines, None))
+        .expect("stream starts");
+    assert!(stream.into_iter().count() > 0);
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert!(engine.limit_exceeded().is_some());
+}
@@ -0,0 +1,61 @@
+use mini_nu::lint::{self, RuleConfig, Severity};
+use mini_nu::EngineBuilder;
+
+#[test]
+fn findings_point_into_the_source() {
+    let engine = EngineBuilder::new()
+        .with_shell_commands()
+        .build()
+        .expect("engine builds");
+    // Spans already taken by the engine shouldn't shift the offsets.
+    let source = "let x = 1\n  cd /tmp\n$env.PWD = '/'";
+    let findings = lint::lint(&engine, source, &RuleConfig::default());
+
+    let found: Vec<_> = findings
+        .iter()
+        .map(|f| (f.rule, f.line, f.column, &source[f.start..f.end]))
+        .collect();
+    assert_eq!(found, [("cd", 2, 3, "cd"), ("env-pwd", 3, 1, "$env.PWD =")]);
+    assert!(findings.iter().all(|f| f.severity == Severity::Warn));
+}
+
+#[test]
+fn rules_take_their_configured_severity() {
+    let engine = EngineBuilder::new()
+        .with_shell_commands()
+        .build()
+        .expect("engine builds");
+    let mut config = RuleConfig::default();
+    config.set("cd", Severity::Off).unwrap();
+    config.set("loop", Severity::Error).unwrap();
+    assert!(config.set("nope", Severity::Error).is_err());
+
+    let findings = lint::lint(&engine, "cd /; loop { break }", &config);
+    let found: Vec<_> = findings.iter().map(|f| (f.rule, f.severity)).collect();
+    assert_eq!(found, [("loop", Severity::Error)]);
+}
+
+#[test]
+fn code_that_cant_run_is_an_error() {
+    let engine = EngineBuilder::new()
+        .with_shell_commands()
+        .build()
+        .expect("engine builds");
+    let config = RuleConfig::default();
+
+    let findings = lint::lint(&engine, "let-env PWD = 1", &config);
+    let found: Vec<_> = findings.iter().map(|f| (f.rule, f.severity)).collect();
+    assert_eq!(found, [(lint::REMOVED_RULE, Severity::Error)]);
+
+    let source = "cd /tmp; [1 2";
+    let findings = lint::lint(&engine, source, &config);
+    let parse: Vec<_> = findings
+        .iter()
+        .filter(|f| f.rule == lint::PARSE_RULE)
+        .collect();
+    assert!(!parse.is_empty());
+    assert!(parse
+        .iter()
+        .all(|f| f.severity == Severity::Error && f.start > source.find('[').unwrap()));
+    assert!(findings.iter().any(|f| f.rule == "cd"));
+}
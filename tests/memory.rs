@@ -0,0 +1,36 @@
+//! The memory limit, which needs the counting allocator installed in the test binary too.
+
+use std::time::Duration;
+
+use mini_nu::memory::CountingAllocator;
+use mini_nu::EngineBuilder;
+use nu_protocol::PipelineData;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn memory_limit_stops_a_growing_stream() {
+    let mut engine = EngineBuilder::new()
+        .with_shell_commands()
+        .max_memory(16 * 1024 * 1024)
+        .timeout(Duration::from_secs(20))
+        .build()
+        .expect("engine builds");
+    let closure = engine.parse_closure("{|x| $x }").expect("closure");
+
+    let stream = engine
+        .eval("1.. | each {|i| 0..100 }", PipelineData::empty())
+        .expect("stream starts");
+    // Holding on to every item grows the heap; the closures don't take the limit over.
+    let kept: Vec<_> = stream
+        .into_iter()
+        .map(|value| engine.eval_closure_with_value(&closure, value))
+        .collect();
+    assert!(!kept.is_empty());
+    let error = engine.limit_exceeded().expect("a limit stopped the stream");
+    assert!(
+        error.to_string().contains("Memory limit exceeded"),
+        "{error:?}"
+    );
+}
@@ -0,0 +1,33 @@
+use mini_nu::notify::NotifySink;
+
+fn sink(toml: &str) -> NotifySink {
+    toml::from_str(toml).expect("sink parses")
+}
+
+#[test]
+fn command_sink_gets_the_message_on_stdin() {
+    let out = std::env::temp_dir().join(format!("mini-nu-notify-{}", std::process::id()));
+    let sink = sink(&format!(
+        "sink = \"cmd\"\nprogram = \"sh\"\nargs = [\"-c\", \"cat > '{}'\"]",
+        out.display()
+    ));
+    sink.send("run failed").expect("sent");
+    assert_eq!(std::fs::read_to_string(&out).unwrap(), "run failed");
+    let _ = std::fs::remove_file(out);
+}
+
+#[test]
+fn command_sink_failures_are_errors() {
+    let failing =
+        sink("sink = \"cmd\"\nprogram = \"sh\"\nargs = [\"-c\", \"cat > /dev/null; exit 3\"]");
+    assert!(failing.send("x").unwrap_err().contains("exited with"));
+
+    let missing = sink("sink = \"cmd\"\nprogram = \"mini-nu-no-such-program\"");
+    assert!(missing.send("x").unwrap_err().starts_with("Can't run"));
+}
+
+#[test]
+fn unknown_sinks_are_rejected() {
+    assert!(toml::from_str::<NotifySink>("sink = \"pager\"").is_err());
+    assert!(matches!(sink("sink = \"stderr\""), NotifySink::Stderr));
+}
@@ -0,0 +1,34 @@
+use mini_nu::profiles::Profile;
+use mini_nu::EngineBuilder;
+use nu_protocol::{PipelineData, Span};
+
+#[test]
+fn profiles_leave_out_side_effects_and_the_clock() {
+    let mut engine = [
+        Profile::Filters,
+        Profile::Strings,
+        Profile::Math,
+        Profile::Data,
+    ]
+    .into_iter()
+    .fold(EngineBuilder::new(), EngineBuilder::with_profile)
+    .build()
+    .expect("engine builds");
+
+    let capabilities = engine.capabilities();
+    for (capability, commands) in capabilities.entries() {
+        if capability != "env_access" {
+            assert!(commands.is_empty(), "{capability}: {commands:?}");
+        }
+    }
+
+    let mut eval = |code: &str| {
+        engine
+            .eval(code, PipelineData::empty())
+            .and_then(|data| data.into_value(Span::unknown()))
+    };
+    assert!(eval("'now' | into datetime").is_err());
+    assert!(eval("date now").is_err());
+    assert!(eval("'2024-01-31T12:00:00+00:00' | into datetime").is_ok());
+    assert!(eval("[3 1 2] | sort | to json --raw").is_ok());
+}
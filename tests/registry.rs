@@ -0,0 +1,54 @@
+use mini_nu::registry::Registry;
+use mini_nu::EngineBuilder;
+
+fn scratch(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("mini-nu-registry-{}-{name}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("scratch dir");
+    dir
+}
+
+#[test]
+fn install_keeps_what_is_installed_unless_told_to_replace() {
+    let engine = EngineBuilder::new()
+        .with_shell_commands()
+        .build()
+        .expect("engine builds");
+    let dir = scratch("replace");
+    let registry = Registry::new(dir.join("closures"));
+    let closure = dir.join("double.nu");
+
+    std::fs::write(&closure, "{|x| $x * 2 }").unwrap();
+    assert_eq!(
+        registry.install(&engine, &closure, false).as_deref(),
+        Ok("double")
+    );
+    std::fs::write(&closure, "{|x| $x * 3 }").unwrap();
+    let err = registry.install(&engine, &closure, false).unwrap_err();
+    assert!(err.contains("already installed"), "{err}");
+    assert_eq!(registry.get("double").unwrap().1, "{|x| $x * 2 }");
+
+    registry.install(&engine, &closure, true).expect("replaced");
+    assert_eq!(registry.get("double").unwrap().1, "{|x| $x * 3 }");
+    assert_eq!(registry.list(), Ok(vec!["double".to_string()]));
+}
+
+#[test]
+fn install_takes_only_names_get_accepts() {
+    let engine = EngineBuilder::new()
+        .with_shell_commands()
+        .build()
+        .expect("engine builds");
+    let dir = scratch("names");
+    let registry = Registry::new(dir.join("closures"));
+
+    for file in [".hidden.nu", "..nu", ".nu"] {
+        let closure = dir.join(file);
+        std::fs::write(&closure, "{|x| $x }").unwrap();
+        assert!(
+            registry.install(&engine, &closure, false).is_err(),
+            "{file} should be refused"
+        );
+    }
+    assert_eq!(registry.list(), Ok(vec![]));
+    assert!(registry.get(".hidden").is_err());
+}
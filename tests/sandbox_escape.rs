@@ -0,0 +1,332 @@
+//! Known ways of getting around a sandbox profile, each of which must fail. Run these after
+//! bumping Nushell: new commands or syntax can reopen a capability the profile takes away.
+
+use std::path::PathBuf;
+
+use mini_nu::{Engine, EngineBuilder};
+use nu_protocol::{PipelineData, ShellError, Span, Value};
+
+/// Attempts to start or signal a process, or change its limits, which would sidestep every
+/// profile.
+const SPAWN_ATTEMPTS: &[&str] = &[
+    "^echo escaped",
+    "run-external echo escaped",
+    "let cmd = 'echo'; ^$cmd escaped",
+    "nu -c 'echo escaped'",
+    "exec echo escaped",
+    "hide run-external; ^echo escaped",
+    "with-env {PATH: '/bin:/usr/bin'} { ^echo escaped }",
+    "[echo] | each {|cmd| run-external $cmd escaped }",
+    "kill 999999",
+    "kill --signal 0 999999",
+    "ulimit -a",
+    "start escaped.txt",
+];
+
+fn engine(profile: fn(EngineBuilder) -> EngineBuilder) -> Engine {
+    profile(EngineBuilder::new().with_shell_commands().with_env())
+        .build()
+        .expect("engine builds")
+}
+
+fn scratch_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mini-nu-escape-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("scratch dir");
+    dir.join(name)
+}
+
+/// Starts of the errors the sandbox refuses something with: a denied command, syntax the policy
+/// rejects, a path outside the jail, a device file or a relative date.
+const REFUSALS: &[&str] = &[
+    "Not allowed by the engine's policy",
+    "is limited to",
+    "can't read device files",
+    "Relative dates are disabled",
+    "`into datetime --list` is disabled",
+];
+
+fn is_refusal(error: &ShellError) -> bool {
+    match error {
+        ShellError::GenericError { error, inner, .. } => {
+            error.ends_with("` is not available")
+                || REFUSALS.iter().any(|refusal| error.contains(refusal))
+                || inner.iter().any(is_refusal)
+        }
+        ShellError::EvalBlockWithInput { sources, .. } => sources.iter().any(is_refusal),
+        // With `run-external` hidden, nu has nothing to run an external command with.
+        ShellError::ExternalNotSupported { .. } => true,
+        _ => false,
+    }
+}
+
+/// Errors raised inside `each` and friends come back as values rather than failing the pipeline.
+fn contains_refusal(value: &Value) -> bool {
+    match value {
+        Value::Error { error, .. } => is_refusal(error),
+        Value::List { vals, .. } => vals.iter().any(contains_refusal),
+        Value::Record { val, .. } => val.values().any(contains_refusal),
+        _ => false,
+    }
+}
+
+#[track_caller]
+fn assert_denied(engine: &mut Engine, code: &str) {
+    let result = engine
+        .eval(code, PipelineData::empty())
+        .and_then(|data| data.into_value(Span::unknown()));
+    let refused = match &result {
+        Ok(value) => contains_refusal(value),
+        Err(error) => is_refusal(error),
+    };
+    assert!(refused, "escaped the sandbox: {code} => {result:?}");
+}
+
+#[test]
+fn read_only_blocks_writes() {
+    let mut engine = engine(EngineBuilder::read_only);
+    let target = scratch_path("read-only");
+    let target = target.display();
+
+    for code in SPAWN_ATTEMPTS {
+        assert_denied(&mut engine, code);
+    }
+    for code in [
+        format!("'x' | save {target}"),
+        format!("'x' o> {target}"),
+        format!("'x' o>> {target}"),
+        format!("do {{ 'x' }} out+err> {target}"),
+        format!("hide save; 'x' | save {target}"),
+        format!("alias s = save; 'x' | s {target}"),
+        format!("touch {target}"),
+        format!("mkdir {target}"),
+        format!("cp Cargo.toml {target}"),
+    ] {
+        assert_denied(&mut engine, &code);
+    }
+    // `tee` runs its closure on another thread and drops its errors, so only the file tells.
+    let _ = engine
+        .eval(
+            &format!("'x' | tee {{ save {target} }}"),
+            PipelineData::empty(),
+        )
+        .and_then(|data| data.into_value(Span::unknown()));
+    assert!(!scratch_path("read-only").exists());
+}
+
+#[test]
+fn deny_filesystem_blocks_reads_and_writes() {
+    let mut engine = engine(EngineBuilder::deny_filesystem);
+    let module = scratch_path("module.nu");
+    std::fs::write(&module, "export def leaked [] { 'escaped' }").expect("write module");
+    let module = module.display();
+    let target = scratch_path("deny-filesystem");
+    let target = target.display();
+
+    for code in SPAWN_ATTEMPTS {
+        assert_denied(&mut engine, code);
+    }
+    for code in [
+        "open Cargo.toml".to_string(),
+        "ls".to_string(),
+        "glob *".to_string(),
+        "'/etc/passwd' | path exists".to_string(),
+        "'/etc/passwd' | path type".to_string(),
+        "'~' | path expand".to_string(),
+        "nu-check /etc/passwd".to_string(),
+        "which sh".to_string(),
+        "config reset --without-backup".to_string(),
+        format!("source {module}"),
+        format!("use {module} leaked; leaked"),
+        format!("overlay use {module}"),
+        format!("'x' o> {target}"),
+    ] {
+        assert_denied(&mut engine, &code);
+    }
+    assert!(!scratch_path("deny-filesystem").exists());
+}
+
+#[test]
+fn jail_blocks_paths_outside_root() {
+    let root = scratch_path("jail");
+    std::fs::create_dir_all(&root).expect("jail dir");
+    std::fs::write(root.join("inside.txt"), "inside").expect("write inside");
+    let mut engine = EngineBuilder::new()
+        .with_shell_commands()
+        .with_env()
+        .jail(&root)
+        .build()
+        .expect("engine builds");
+    let outside = scratch_path("outside.txt");
+    std::fs::write(&outside, "outside").expect("write outside");
+    let outside = outside.display();
+
+    for code in SPAWN_ATTEMPTS {
+        assert_denied(&mut engine, code);
+    }
+    for code in [
+        format!("open {outside}"),
+        format!("'{outside}' | open"),
+        "'/etc/hostname' | open".to_string(),
+        "'../outside.txt' | open".to_string(),
+        "open ../outside.txt".to_string(),
+        "ls ..".to_string(),
+        "ls ~".to_string(),
+        "ls /".to_string(),
+        "'x' | save ../escaped.txt".to_string(),
+        "'x' | save inside.txt --force --stderr ../escaped.txt".to_string(),
+        "cd ..".to_string(),
+        "glob ../*".to_string(),
+    ] {
+        assert_denied(&mut engine, &code);
+    }
+    assert!(!scratch_path("escaped.txt").exists());
+
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(scratch_path("outside.txt"), root.join("link"))
+            .expect("symlink");
+        for code in [
+            "open link",
+            "open lin*",
+            "open l?nk",
+            "ls lin*",
+            "'lin*' | open",
+        ] {
+            assert_denied(&mut engine, code);
+        }
+    }
+
+    let inside = engine
+        .eval("open inside.txt", PipelineData::empty())
+        .and_then(|data| data.into_value(Span::unknown()));
+    assert_eq!(
+        inside.and_then(|value| value.into_string()).ok().as_deref(),
+        Some("inside")
+    );
+    let piped = engine
+        .eval("'inside.txt' | open", PipelineData::empty())
+        .and_then(|data| data.into_value(Span::unknown()));
+    assert_eq!(
+        piped.and_then(|value| value.into_string()).ok().as_deref(),
+        Some("inside")
+    );
+}
+
+#[test]
+fn jail_does_not_undo_other_denials() {
+    let root = scratch_path("jail-denials");
+    std::fs::create_dir_all(&root).expect("jail dir");
+    std::fs::write(root.join("inside.txt"), "inside").expect("write inside");
+    let jailed = || EngineBuilder::new().with_shell_commands().jail(&root);
+
+    for (builder, code) in [
+        (jailed().read_only(), "'x' | save --force written.txt"),
+        (jailed().deny(["save"]), "'x' | save --force written.txt"),
+        (jailed().allow(["length"]), "open inside.txt"),
+        (jailed().allow(["length"]), "ls"),
+    ] {
+        let mut engine = builder.build().expect("engine builds");
+        assert_denied(&mut engine, code);
+    }
+    assert!(!root.join("written.txt").exists());
+}
+
+#[test]
+fn deterministic_jail_refuses_device_files() {
+    let mut engine = EngineBuilder::new()
+        .with_shell_commands()
+        .jail("/")
+        .deterministic()
+        .build()
+        .expect("engine builds");
+
+    for code in [
+        "open /dev/urandom",
+        "open /proc/uptime",
+        "'/dev/urandom' | open | first 4",
+        "'/proc/uptime' | open",
+        "'/dev/uran*' | open",
+    ] {
+        assert_denied(&mut engine, code);
+    }
+}
+
+#[test]
+fn deny_network_blocks_requests() {
+    let mut engine = engine(EngineBuilder::deny_network);
+
+    for code in SPAWN_ATTEMPTS {
+        assert_denied(&mut engine, code);
+    }
+    for code in [
+        "http get http://127.0.0.1:9",
+        "http post http://127.0.0.1:9 body",
+        "port",
+        "start https://example.com/x",
+    ] {
+        assert_denied(&mut engine, code);
+    }
+}
+
+#[test]
+fn deterministic_blocks_entropy() {
+    let mut engine = engine(EngineBuilder::deterministic);
+
+    for code in SPAWN_ATTEMPTS {
+        assert_denied(&mut engine, code);
+    }
+    for code in [
+        "random int",
+        "random uuid",
+        "date now",
+        "sys host",
+        "seq date --days 1",
+        "ps",
+        "^date",
+        "'now' | into datetime",
+        "'today' | into datetime",
+        "'3 days ago' | into datetime",
+        "'10:30' | into datetime",
+        "['2024-01-31' 'tomorrow'] | into datetime",
+        "[[when]; [yesterday]] | into datetime when",
+        "into datetime --list",
+        "timeit { 1 }",
+        "open /dev/urandom",
+        "open '/dev/urandom'",
+        "open /dev/uran*",
+        "open /proc/uptime",
+        "'/dev/urandom' | open | first 4",
+        "'/proc/uptime' | open",
+        "open /etc/hostname",
+        "ls | length",
+        "$nu.pid",
+        "$nu.home-path",
+        "do { $nu } | get os-info",
+    ] {
+        assert_denied(&mut engine, code);
+    }
+    let env = engine
+        .eval("$env | columns", PipelineData::empty())
+        .and_then(|data| data.into_value(Span::unknown()));
+    assert!(
+        !env.is_ok_and(|columns| columns
+            .into_list()
+            .unwrap()
+            .contains(&Value::test_string("PWD"))),
+        "the host's $env was gathered"
+    );
+
+    let absolute = engine
+        .eval(
+            "'2024-01-31T12:00:00+00:00' | into datetime | format date '%s'",
+            PipelineData::empty(),
+        )
+        .and_then(|data| data.into_value(Span::unknown()));
+    assert_eq!(
+        absolute
+            .and_then(|value| value.into_string())
+            .ok()
+            .as_deref(),
+        Some("1706702400")
+    );
+}
@@ -0,0 +1,126 @@
+use mini_nu::secrets::{SecretProvider, SecretStore};
+use mini_nu::EngineBuilder;
+use nu_protocol::{PipelineData, Span};
+
+#[test]
+fn file_provider_stays_in_its_dir() {
+    let root = std::env::temp_dir().join(format!("mini-nu-secrets-{}", std::process::id()));
+    let dir = root.join("secrets");
+    std::fs::create_dir_all(&dir).expect("secrets dir");
+    std::fs::write(dir.join("token"), "hunter2\n").expect("write secret");
+    std::fs::write(root.join("outside"), "outside").expect("write outside");
+    let provider = SecretProvider::File { dir: dir.clone() };
+
+    assert_eq!(provider.fetch("token").as_deref(), Ok("hunter2"));
+    for name in [
+        "/etc/hostname",
+        "../outside",
+        "./../outside",
+        "sub/token",
+        ".",
+        "",
+    ] {
+        assert!(provider.fetch(name).is_err(), "{name} should be refused");
+    }
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(root.join("outside"), dir.join("link")).expect("symlink");
+        assert!(provider.fetch("link").is_err());
+    }
+
+    let mut engine = EngineBuilder::new()
+        .with_shell_commands()
+        .deny_filesystem()
+        .build()
+        .expect("engine builds");
+    engine
+        .add_secrets(SecretStore::new(provider))
+        .expect("secrets");
+    let leaked = engine
+        .eval(
+            "secret get '/etc/hostname' | secret reveal",
+            PipelineData::empty(),
+        )
+        .and_then(|data| data.into_value(Span::unknown()));
+    assert!(leaked.is_err(), "read a file outside the dir: {leaked:?}");
+}
+
+#[test]
+fn command_provider_refuses_options() {
+    let provider = SecretProvider::Command {
+        program: "echo".into(),
+    };
+    assert_eq!(provider.fetch("token").as_deref(), Ok("token"));
+    for name in ["-n", "--help", "-"] {
+        assert!(provider.fetch(name).is_err(), "{name} should be refused");
+    }
+}
+
+#[cfg(feature = "vault")]
+#[test]
+fn vault_names_stay_in_the_mount() {
+    let provider = SecretProvider::Vault {
+        addr: "http://127.0.0.1:9".into(),
+        mount: "secret".into(),
+    };
+    for name in [
+        "../sys/health",
+        "app/../../sys",
+        "/app",
+        "app//db",
+        "app/",
+        "",
+        "app?version=1",
+        "app#key#more",
+        "app#",
+        "app%2f..",
+        "app/./db",
+    ] {
+        let err = provider.fetch(name).unwrap_err();
+        assert!(err.starts_with("Invalid secret name"), "{name}: {err}");
+    }
+    // Valid names get as far as looking for the token or asking the server.
+    let err = provider.fetch("app/db.prod#password").unwrap_err();
+    assert!(!err.starts_with("Invalid secret name"), "{err}");
+}
+
+#[test]
+fn secrets_only_unmask_through_reveal() {
+    let dir = std::env::temp_dir().join(format!("mini-nu-reveal-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("secrets dir");
+    std::fs::write(dir.join("token"), "hunter2").expect("write secret");
+    let mut engine = EngineBuilder::new()
+        .with_shell_commands()
+        .with_env()
+        .build()
+        .expect("engine builds");
+    engine
+        .add_secrets(SecretStore::new(SecretProvider::File { dir }))
+        .expect("secrets");
+    let mut eval = |code: &str| {
+        engine
+            .eval(code, PipelineData::empty())
+            .and_then(|data| data.into_value(Span::unknown()))
+            .and_then(|value| value.coerce_into_string())
+    };
+
+    assert_eq!(
+        eval(r#"$"Bearer (secret get token)""#).as_deref(),
+        Ok("Bearer ********")
+    );
+    assert_eq!(
+        eval("secret get token | to text").as_deref(),
+        Ok("********")
+    );
+    assert!(eval("^echo (secret get token)").is_err());
+
+    assert_eq!(
+        eval(r#"$"Bearer (secret get token | secret reveal)""#).as_deref(),
+        Ok("Bearer hunter2")
+    );
+    #[cfg(unix)]
+    assert_eq!(
+        eval("^echo (secret get token | secret reveal)").as_deref(),
+        Ok("hunter2")
+    );
+}
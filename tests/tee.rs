@@ -0,0 +1,46 @@
+use mini_nu::tee::{tee, Tee};
+use mini_nu::EngineBuilder;
+use nu_protocol::Value;
+
+#[test]
+fn tees_keep_their_order_and_their_errors() {
+    let mut engine = EngineBuilder::new()
+        .with_shell_commands()
+        .build()
+        .expect("engine builds");
+    let tees = [
+        ("slow", "{|x| sleep 50ms; $x + 1 }"),
+        ("failing", "{|x| error make {msg: 'tee failed'} }"),
+        ("fast", "{|x| $x * 2 }"),
+    ]
+    .into_iter()
+    .map(|(name, source)| Tee::new(&mut engine, name, source).expect("tee parses"))
+    .collect::<Vec<_>>();
+
+    for n in 1..=3 {
+        let outputs = tee(&tees, Value::test_int(n));
+        let names: Vec<_> = outputs
+            .iter()
+            .map(|output| {
+                output
+                    .get_data_by_key("tee")
+                    .and_then(|v| v.coerce_string().ok())
+            })
+            .collect();
+        assert_eq!(
+            names,
+            ["slow", "failing", "fast"].map(|name| Some(name.to_string()))
+        );
+        let results: Vec<_> = outputs
+            .iter()
+            .map(|output| output.get_data_by_key("result").expect("result"))
+            .collect();
+        assert_eq!(results[0], Value::test_int(n + 1));
+        assert!(
+            matches!(&results[1], Value::Error { error, .. } if error.to_string() == "tee failed"),
+            "{:?}",
+            results[1]
+        );
+        assert_eq!(results[2], Value::test_int(n * 2));
+    }
+}
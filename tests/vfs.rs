@@ -0,0 +1,87 @@
+use std::sync::Arc;
+
+use mini_nu::vfs::{DirFs, MemoryFs, ReadOnly};
+use mini_nu::{Engine, EngineBuilder};
+use nu_protocol::{PipelineData, ShellError, Span, Value};
+
+fn eval(engine: &mut Engine, code: &str) -> Result<Value, ShellError> {
+    engine
+        .eval(code, PipelineData::empty())
+        .and_then(|data| data.into_value(Span::unknown()))
+}
+
+#[test]
+fn memory_fs_round_trips() {
+    let fs = Arc::new(MemoryFs::new());
+    fs.insert("in/data.json", r#"{"n": 1}"#).expect("insert");
+    let mut engine = EngineBuilder::new()
+        .with_shell_commands()
+        .vfs(fs.clone())
+        .build()
+        .expect("engine builds");
+
+    let n = eval(&mut engine, "open /in/data.json | get n").expect("open parses json");
+    assert_eq!(n.as_int().ok(), Some(1));
+
+    eval(&mut engine, "{n: 2} | save out/result.json").expect("save");
+    let saved = fs.get("out/result.json").expect("saved into the vfs");
+    assert!(String::from_utf8(saved).unwrap().contains("\"n\": 2"));
+    assert!(eval(&mut engine, "'x' | save out/result.json").is_err());
+
+    let names = eval(&mut engine, "ls | get name").expect("ls");
+    assert_eq!(
+        names.into_list().unwrap(),
+        vec![Value::test_string("in"), Value::test_string("out")]
+    );
+
+    for code in ["open ../data.json", "open ~/.ssh/id_rsa", "ls /etc/.."] {
+        assert!(eval(&mut engine, code).is_err(), "{code} should fail");
+    }
+}
+
+#[test]
+fn dir_fs_stays_under_root_and_read_only_refuses_writes() {
+    let root = std::env::temp_dir().join(format!("mini-nu-vfs-{}", std::process::id()));
+    std::fs::create_dir_all(&root).expect("root dir");
+    std::fs::write(root.join("inside.txt"), "inside").expect("write inside");
+    let fs = ReadOnly(DirFs::new(&root).expect("dir fs"));
+    let mut engine = EngineBuilder::new()
+        .with_shell_commands()
+        .vfs(Arc::new(fs))
+        .build()
+        .expect("engine builds");
+
+    let inside = eval(&mut engine, "open inside.txt").expect("open");
+    assert_eq!(inside.into_string().ok().as_deref(), Some("inside"));
+
+    for code in [
+        "open /etc/passwd",
+        "open ../inside.txt",
+        "'x' | save new.txt",
+        "'x' o> new.txt",
+    ] {
+        assert!(eval(&mut engine, code).is_err(), "{code} should fail");
+    }
+    assert!(!root.join("new.txt").exists());
+}
+
+#[test]
+fn vfs_does_not_undo_other_denials() {
+    let fs = Arc::new(MemoryFs::new());
+    fs.insert("in.txt", "in").expect("insert");
+    let mut engine = EngineBuilder::new()
+        .with_shell_commands()
+        .vfs(fs.clone())
+        .read_only()
+        .deny(["ls"])
+        .build()
+        .expect("engine builds");
+
+    assert_eq!(
+        eval(&mut engine, "open in.txt").ok(),
+        Some(Value::test_string("in"))
+    );
+    assert!(eval(&mut engine, "'x' | save out.txt").is_err());
+    assert!(fs.get("out.txt").is_none(), "read_only let save write");
+    assert!(eval(&mut engine, "ls").is_err());
+}